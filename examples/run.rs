@@ -4,23 +4,39 @@ use rayon::iter::{ParallelBridge, ParallelIterator};
 use firecore_world_gen::*;
 
 const PARSED: &str = "output/parsed.bin";
+const PARSED_NAMESPACED: &str = "output/parsed-namespaced.bin";
 
 fn main() -> anyhow::Result<()> {
-    let mappings = ron::from_str(&std::fs::read_to_string("./mappings.ron")?)?;
+    let mappings: NameMappings = ron::from_str(&std::fs::read_to_string("./mappings.ron")?)?;
 
     let edits = ron::from_str(&std::fs::read_to_string("./edits.ron")?)?;
 
-    fn load() -> anyhow::Result<ParsedData> {
-        anyhow::Result::<ParsedData>::Ok(postcard::from_bytes::<ParsedData>(&std::fs::read(PARSED)?)?)
+    let parse_options = ParseOptions {
+        namespace_scripts: std::env::args().any(|arg| arg == "--namespace-scripts"),
+    };
+
+    // labels are namespaced while parsing, so both kinds of parsed data are kept
+    let parsed = match parse_options.namespace_scripts {
+        true => PARSED_NAMESPACED,
+        false => PARSED,
+    };
+
+    fn load(parsed: &str) -> anyhow::Result<ParsedData> {
+        anyhow::Result::<ParsedData>::Ok(postcard::from_bytes::<ParsedData>(&std::fs::read(parsed)?)?)
     }
 
-    let data = load().or_else::<anyhow::Error, _>(|_| {
-        let data = create_data()?;
-        std::fs::write(PARSED, &postcard::to_allocvec(&data)?)?;
+    let data = load(parsed).or_else::<anyhow::Error, _>(|_| {
+        let data = create_data(parse_options)?;
+        std::fs::write(parsed, &postcard::to_allocvec(&data)?)?;
         Ok(data)
     })?;
 
-    let data = compile(mappings, edits, data).unwrap();
+    let options = CompileOptions {
+        numeric_ids: std::env::args().any(|arg| arg == "--numeric-ids"),
+        keep_unsupported: std::env::args().any(|arg| arg == "--keep-unsupported"),
+    };
+
+    let data = compile(&mappings, edits, data, options).unwrap();
 
     let root = Path::new("output");
 
@@ -75,5 +91,46 @@ fn main() -> anyhow::Result<()> {
         ron::ser::to_string_pretty(&data.scripts, Default::default())?,
     )?;
 
+    std::fs::write(
+        scriptdir.join("scripts.inc"),
+        decompile(&mappings, &data.items, &data.scripts),
+    )?;
+
+    std::fs::write(
+        scriptdir.join("world.bin"),
+        postcard::to_allocvec(&data.world_scripts)?,
+    )?;
+
+    std::fs::write(
+        scriptdir.join("variables.bin"),
+        postcard::to_allocvec(&data.variables)?,
+    )?;
+
+    std::fs::write(
+        scriptdir.join("variables.ron"),
+        ron::ser::to_string_pretty(&data.variables, Default::default())?,
+    )?;
+
+    let trainerdir = root.join("trainers");
+
+    if !trainerdir.exists() {
+        std::fs::create_dir_all(&trainerdir)?;
+    }
+
+    std::fs::write(
+        trainerdir.join("trainers.bin"),
+        postcard::to_allocvec(&data.trainers)?,
+    )?;
+
+    std::fs::write(
+        trainerdir.join("trainers.ron"),
+        ron::ser::to_string_pretty(&data.trainers, Default::default())?,
+    )?;
+
+    std::fs::write(
+        root.join("report.ron"),
+        ron::ser::to_string_pretty(&data.report, Default::default())?,
+    )?;
+
     Ok(())
 }
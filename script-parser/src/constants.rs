@@ -0,0 +1,200 @@
+use hashbrown::HashMap;
+
+/// Value of every object-like `#define` and `enum` member of a C header that can be evaluated.
+///
+/// Expressions may refer to constants defined earlier in the file or already in `constants`,
+/// so headers that build on each other should be parsed in order into the same map.
+pub fn parse_constants(file: &str, constants: &mut HashMap<String, i64>) {
+    let mut enumeration: Option<i64> = None;
+    let mut lines = file.lines();
+
+    while let Some(text) = lines.next() {
+        let mut text = text.split_once("//").map(|(l, ..)| l).unwrap_or(text).to_owned();
+
+        while text.ends_with('\\') {
+            text.pop();
+            match lines.next() {
+                Some(next) => text.push_str(next),
+                None => break,
+            }
+        }
+
+        let text = text.trim();
+
+        if let Some(define) = text.strip_prefix("#define") {
+            let define = define.trim();
+            let (name, expression) = define
+                .split_once(char::is_whitespace)
+                .unwrap_or((define, ""));
+            // function-like macros are not constants
+            if name.contains('(') {
+                continue;
+            }
+            if let Some(value) = evaluate(expression, constants) {
+                constants.insert(name.to_owned(), value);
+            }
+            continue;
+        }
+
+        if text.starts_with("enum") && !text.ends_with(';') {
+            enumeration = Some(0);
+            continue;
+        }
+
+        if let Some(next) = enumeration.as_mut() {
+            if text.starts_with('}') {
+                enumeration = None;
+                continue;
+            }
+            for member in text.split(',').map(str::trim) {
+                if member.is_empty() || member == "{" {
+                    continue;
+                }
+                let (name, value) = match member.split_once('=') {
+                    Some((name, expression)) => match evaluate(expression, constants) {
+                        Some(value) => (name.trim(), value),
+                        None => continue,
+                    },
+                    None => (member, *next),
+                };
+                constants.insert(name.to_owned(), value);
+                *next = value + 1;
+            }
+        }
+    }
+}
+
+/// Evaluates an integer constant expression such as `(TEMP_FLAGS_START + 0x1)`
+pub fn evaluate(expression: &str, constants: &HashMap<String, i64>) -> Option<i64> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        constants,
+    };
+    let value = parser.expression(0)?;
+    (parser.position == tokens.len()).then_some(value)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Number(i64),
+    Name(&'a str),
+    Operator(&'static str),
+    Open,
+    Close,
+}
+
+const OPERATORS: [&str; 9] = ["<<", ">>", "+", "-", "*", "/", "|", "&", "~"];
+
+fn tokenize(expression: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim();
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = rest.trim_start();
+            continue;
+        }
+        let length = match c {
+            '(' => {
+                tokens.push(Token::Open);
+                1
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                1
+            }
+            '0'..='9' => {
+                let length = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(rest.len());
+                let number = rest[..length].trim_end_matches(['u', 'U', 'l', 'L']);
+                let number = match number.strip_prefix("0x").or_else(|| number.strip_prefix("0X")) {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => number.parse(),
+                }
+                .ok()?;
+                tokens.push(Token::Number(number));
+                length
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let length = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Name(&rest[..length]));
+                length
+            }
+            _ => {
+                let operator = OPERATORS.iter().find(|op| rest.starts_with(*op))?;
+                tokens.push(Token::Operator(operator));
+                operator.len()
+            }
+        };
+        rest = &rest[length..];
+    }
+    Some(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    position: usize,
+    constants: &'a HashMap<String, i64>,
+}
+
+impl<'a> Parser<'a> {
+    fn binding(operator: &str) -> u8 {
+        match operator {
+            "|" => 1,
+            "&" => 2,
+            "<<" | ">>" => 3,
+            "+" | "-" => 4,
+            _ => 5,
+        }
+    }
+
+    fn expression(&mut self, min: u8) -> Option<i64> {
+        let mut left = self.unary()?;
+        while let Some(Token::Operator(operator)) = self.tokens.get(self.position) {
+            let binding = Self::binding(operator);
+            if binding <= min {
+                break;
+            }
+            self.position += 1;
+            let right = self.expression(binding)?;
+            left = match *operator {
+                "<<" => left.checked_shl(right.try_into().ok()?)?,
+                ">>" => left.checked_shr(right.try_into().ok()?)?,
+                "+" => left.checked_add(right)?,
+                "-" => left.checked_sub(right)?,
+                "*" => left.checked_mul(right)?,
+                "/" => left.checked_div(right)?,
+                "|" => left | right,
+                "&" => left & right,
+                _ => return None,
+            };
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        match token {
+            Token::Number(number) => Some(*number),
+            Token::Name(name) => self.constants.get(*name).copied(),
+            Token::Operator("-") => self.unary().map(|value| -value),
+            Token::Operator("~") => self.unary().map(|value| !value),
+            Token::Open => {
+                let value = self.expression(0)?;
+                match self.tokens.get(self.position) {
+                    Some(Token::Close) => {
+                        self.position += 1;
+                        Some(value)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
@@ -2,255 +2,701 @@ use hashbrown::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-// #[cfg(test)]
-// mod tests {
-//     #[test]
-//     fn it_works() {
-
-//     }
-// }
-
-pub fn parse(script: &str) -> Result<Vec<Script>, Error> {
-    let mut scripts = Vec::new();
-    let mut aliases = HashMap::new();
-    let mut lines = script.lines().enumerate().peekable();
-    'lines: while let Some((line, text)) = lines.next() {
-        let mut args = text.split_whitespace();
-        if let Some(arg0) = args.next() {
-            match arg0 {
-                ".set" | ".equ" => {
-                    let name = args.next().ok_or(Error::SetName(line))?;
-                    let variable = args.next().ok_or(Error::SetVariable(line))?;
-                    aliases.insert(name.to_owned(), variable.to_owned());
-                }
-                name => {
-                    {
-                        let name = name.trim();
-                        if name.len() < 4
-                            || name
-                                .split_once('@')
-                                .map(|(l, ..)| l.trim().len() < 2)
-                                .unwrap_or_default()
-                            || name == ".align"
-                        {
-                            continue 'lines;
-                        }
-                    }
-                    // let name = args.next().ok_or(Error::ScriptName(line))?;
-                    let end = &name.get(name.len().saturating_sub(2)..);
-                    if !end
-                        .map(|end| end.eq_ignore_ascii_case("::"))
-                        .unwrap_or_default()
-                    {
-                        return Err(Error::ScriptName(line, ScriptNameReason::Name));
-                    }
-                    let name = &name[..name.len() - 2];
-                    let location = location(&mut args, line)?;
-                    let mut commands = Vec::new();
-                    // let mut finished = None;
-                    'commands: loop {
-                        match lines.next() {
-                            Some((.., text)) => {
-                                if lines
-                                    .peek()
-                                    .map(|(.., next)| next.contains("::"))
-                                    .unwrap_or(true)
-                                {
-                                    // println!("broke after {:?} lines", finished);
-                                    break 'commands;
-                                }
-
-                                if let Some(command) = text.split_whitespace().next() {
-                                    let arguments = text
-                                        .split_once(command)
-                                        .map(|(.., arguments)| {
-                                            arguments
-                                                .split(',')
-                                                .map(str::trim)
-                                                .map(|s| {
-                                                    aliases.get(s).map(|s| s.as_str()).unwrap_or(s)
-                                                })
-                                                .map(str::to_owned)
-                                                .collect()
-                                        })
-                                        .unwrap_or_default();
-                                    commands.push(Command {
-                                        command: command.to_owned(),
-                                        arguments,
-                                    });
-                                }
-                            }
-                            None => return Err(Error::EndOfFile("script")),
-                        }
-                    }
+use crate::text::{self, TextLine};
 
-                    let script = Script {
-                        name: name.to_owned(),
-                        location,
-                        commands,
-                    };
+use self::lexer::{Token, TokenKind};
 
-                    scripts.push(script);
-                }
-            }
-        }
+pub mod lexer;
+
+/// Directives that only matter to the assembler
+const IGNORED_DIRECTIVES: [&str; 5] = [".align", ".balign", ".global", ".section", ".text"];
+
+/// How deep includes and macro expansions may nest
+const MAX_DEPTH: usize = 16;
+
+/// Parses event scripts, where every label (`Name::` or the local `Name:`) starts a script.
+///
+/// Errors do not stop parsing: the rest of the line is skipped and every error is returned with the scripts.
+/// `.include` directives are skipped, see [`parse_with`] to follow them.
+pub fn parse(script: &str) -> (Vec<Script>, Vec<Diagnostic>) {
+    parse_with(script, |_| Ok(None))
+}
+
+/// Parses event scripts like [`parse`], reading the file of every `.include` through `include`,
+/// which can skip a file by returning `Ok(None)`.
+///
+/// A file `include` could not read is reported with the error it returns.
+pub fn parse_with(
+    script: &str,
+    mut include: impl FnMut(&str) -> Result<Option<String>, String>,
+) -> (Vec<Script>, Vec<Diagnostic>) {
+    let mut parser = Parser {
+        include: &mut include,
+        scripts: Vec::new(),
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        current: None,
+        defining: None,
+        diagnostics: Vec::new(),
+    };
+
+    parser.source(script, Origin::default(), 0);
+
+    if let Some((name, span, ..)) = parser.defining.take() {
+        parser.diagnostics.push(Diagnostic::new(
+            span,
+            format!("macro {} has no .endm", name),
+        ));
     }
-    Ok(scripts)
+
+    let Parser {
+        mut scripts,
+        current,
+        mut diagnostics,
+        ..
+    } = parser;
+
+    scripts.extend(current);
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span);
+
+    (scripts, diagnostics)
 }
 
-pub fn parse_message_script(script: &str) -> Result<Vec<Message>, Error> {
-    let mut messages = Vec::new();
-    let mut lines = script.lines().enumerate();
-    while let Some((line, text)) = lines.next() {
-        let mut args = text.split_whitespace();
-        if let Some(name) = args.next() {
-            if name.trim().len() < 4 {
-                continue;
-            }
-            // let name = args.next().ok_or(Error::ScriptName(line))?;
-            let end = &name[name.len() - 2..];
-            if !end.eq_ignore_ascii_case("::") {
-                return Err(Error::ScriptName(line, ScriptNameReason::Name));
-            }
-            let name = &name[..name.len() - 2];
-            
-            let location = location(&mut args, line)?;
+struct Parser<'a> {
+    include: &'a mut dyn FnMut(&str) -> Result<Option<String>, String>,
+    scripts: Vec<Script>,
+    aliases: HashMap<String, String>,
+    macros: HashMap<String, Macro>,
+    current: Option<Script>,
+    /// The macro between `.macro` and `.endm`
+    defining: Option<(String, Span, Macro)>,
+    diagnostics: Vec<Diagnostic>,
+}
 
-            let mut message_pages = Vec::new();
-            let mut message_lines = Vec::new();
-            'message: loop {
-                match lines.next() {
-                    Some((line, text)) => {
-                        let command = text
-                            .split_whitespace()
-                            .next()
-                            .ok_or(Error::NoCommand(line))?;
+#[derive(Debug, Clone)]
+struct Macro {
+    parameters: Vec<Parameter>,
+    /// Source lines, expanded by replacing `\parameter` and `\()`
+    body: Vec<String>,
+}
 
-                        let text = text.split(command).last().ok_or(Error::NoArguments(line))?;
+#[derive(Debug, Clone)]
+struct Parameter {
+    name: String,
+    /// Written `name:req`
+    required: bool,
+    /// Written `name=default`
+    default: Option<String>,
+}
 
-                        let text = text.trim();
+/// Where the source being parsed comes from
+#[derive(Debug, Default, Clone, Copy)]
+struct Origin<'a> {
+    /// The included file, if not the one given to the parser
+    file: Option<&'a str>,
+    /// The invocation of the macro the source was expanded from
+    expansion: Option<Span>,
+}
 
-                        let mut text = text.split("\"");
+impl Origin<'_> {
+    fn span(&self, span: Span) -> Span {
+        self.expansion.unwrap_or(span)
+    }
 
-                        text.next();
+    fn diagnostic(&self, span: Span, message: impl Into<String>) -> Diagnostic {
+        let message = message.into();
+        Diagnostic::new(
+            self.span(span),
+            match self.file {
+                Some(file) => format!("in {}: {}", file, message),
+                None => message,
+            },
+        )
+    }
+}
 
-                        let text = text.next().ok_or(Error::NoArguments(line))?;
+impl Parser<'_> {
+    fn source(&mut self, source: &str, origin: Origin, depth: usize) {
+        let (tokens, diagnostics) = lexer::lex(source);
 
-                        let text = text.replace("{PLAYER}", "%p");
+        self.diagnostics.extend(
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| origin.diagnostic(diagnostic.span, diagnostic.message)),
+        );
 
-                        #[deprecated]
-                        let text = text.replace('é', "E");
+        for line in tokens.split(|token| token.kind == TokenKind::Newline) {
+            self.line(source, line, origin, depth);
+        }
+    }
 
-                        if text.contains("$") {
-                            let line = text.replace('$', "");
-                            message_lines.push(line);
-                            message_pages.push(std::mem::take(&mut message_lines));
-                            break 'message;
-                        }
+    fn line(&mut self, source: &str, line: &[Token], origin: Origin, depth: usize) {
+        let comment = line.iter().find_map(|token| match token.kind {
+            TokenKind::Comment(comment) => Some(comment),
+            _ => None,
+        });
 
-                        if text.contains("\\p") {
-                            let line = text.replace("\\p", "");
-                            message_lines.push(line);
-                            message_pages.push(std::mem::take(&mut message_lines));
-                        }
+        let line = line
+            .iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Comment(..)))
+            .collect::<Vec<_>>();
+
+        if let Some((.., definition)) = self.defining.as_mut() {
+            match (line.first(), line.last()) {
+                (
+                    Some(Token {
+                        kind: TokenKind::Ident(".endm"),
+                        ..
+                    }),
+                    ..,
+                ) => {
+                    if let Some((name, .., definition)) = self.defining.take() {
+                        self.macros.insert(name, definition);
+                    }
+                }
+                (Some(first), Some(last)) => definition
+                    .body
+                    .push(source[first.range.start..last.range.end].to_owned()),
+                _ => (),
+            }
+            return;
+        }
+
+        let mut line = line.as_slice();
+
+        // labels, which may be followed by a statement
+        while let [Token {
+            kind: TokenKind::Ident(name),
+            span,
+            ..
+        }, Token {
+            kind: TokenKind::Colon | TokenKind::DoubleColon,
+            ..
+        }, rest @ ..] = line
+        {
+            self.scripts.extend(self.current.take());
+            self.current = Some(Script {
+                name: (*name).to_owned(),
+                // an old style address comment, `Name:: @ 81A7ADB`
+                location: comment
+                    .filter(|_| rest.is_empty())
+                    .and_then(|comment| Location::from_str_radix(comment, 16).ok()),
+                commands: Vec::new(),
+                data: Vec::new(),
+                span: origin.span(*span),
+            });
+            line = rest;
+        }
+
+        let (name, span, rest) = match line {
+            [] => return,
+            [Token {
+                kind: TokenKind::Ident(name),
+                span,
+                ..
+            }, rest @ ..] => (*name, *span, rest),
+            [token, ..] => {
+                self.diagnostics.push(origin.diagnostic(
+                    token.span,
+                    format!("expected a label or command, found {:?}", token.kind),
+                ));
+                return;
+            }
+        };
+
+        let arguments = match arguments(source, rest) {
+            Ok(arguments) => arguments,
+            Err(diagnostic) => {
+                self.diagnostics
+                    .push(origin.diagnostic(diagnostic.span, diagnostic.message));
+                return;
+            }
+        };
 
-                        for terminator in ["\\n", "\\l"] {
-                            if text.contains(terminator) {
-                                let line = text.replace(terminator, "");
-                                message_lines.push(line);
-                            }
+        match name {
+            ".set" | ".equ" => match arguments.as_slice() {
+                [name, value] => {
+                    self.aliases.insert(name.clone(), value.clone());
+                }
+                _ => self
+                    .diagnostics
+                    .push(origin.diagnostic(span, format!("{} takes a name and a value", name))),
+            },
+            ".include" => match arguments.as_slice() {
+                [file] if depth < MAX_DEPTH => {
+                    let file = unquote(file);
+                    match (self.include)(file) {
+                        Ok(Some(included)) => {
+                            let origin = Origin {
+                                file: Some(file),
+                                expansion: None,
+                            };
+                            self.source(&included, origin, depth + 1);
                         }
+                        Ok(None) => (),
+                        Err(error) => self.diagnostics.push(
+                            origin
+                                .diagnostic(span, format!("could not include {}: {}", file, error)),
+                        ),
                     }
-                    None => return Err(Error::EndOfFile("message")),
                 }
+                [file] => self
+                    .diagnostics
+                    .push(origin.diagnostic(span, format!("{} is included too deep", file))),
+                _ => self
+                    .diagnostics
+                    .push(origin.diagnostic(span, ".include takes a file")),
+            },
+            ".macro" => self.define(&arguments, span, origin),
+            ".endm" => self
+                .diagnostics
+                .push(origin.diagnostic(span, ".endm is not after a .macro")),
+            ".byte" | ".2byte" | ".hword" | ".4byte" | ".word" | ".string" | ".braille" => {
+                let data = match name {
+                    ".byte" => Data::Byte(self.resolve(arguments)),
+                    ".2byte" | ".hword" => Data::Half(self.resolve(arguments)),
+                    ".4byte" | ".word" => Data::Word(self.resolve(arguments)),
+                    ".string" => Data::String(arguments.iter().map(|text| unquote(text)).collect()),
+                    _ => Data::Braille(arguments.iter().map(|text| unquote(text)).collect()),
+                };
+                match self.current.as_mut() {
+                    Some(current) => current.data.push(data),
+                    None => self
+                        .diagnostics
+                        .push(origin.diagnostic(span, format!("{} is not in a script", name))),
+                }
+            }
+            name if IGNORED_DIRECTIVES.contains(&name) => (),
+            name if self.macros.contains_key(name) => {
+                self.expand(name, arguments, span, origin, depth)
+            }
+            command => {
+                let arguments = self.resolve(arguments);
+                match self.current.as_mut() {
+                    Some(current) => current.commands.push(Command {
+                        command: command.to_owned(),
+                        arguments,
+                        span: origin.span(span),
+                    }),
+                    None => self
+                        .diagnostics
+                        .push(origin.diagnostic(span, format!("{} is not in a script", command))),
+                }
+            }
+        }
+    }
+
+    /// Replaces the arguments that are names of `.set` aliases
+    fn resolve(&self, arguments: Vec<String>) -> Vec<String> {
+        arguments
+            .into_iter()
+            .map(|argument| self.aliases.get(&argument).cloned().unwrap_or(argument))
+            .collect()
+    }
+
+    /// Starts a `.macro name param1, param2:req, param3=default` definition
+    fn define(&mut self, arguments: &[String], span: Span, origin: Origin) {
+        // the name and the first parameter are only separated by whitespace
+        let mut words = arguments
+            .iter()
+            .flat_map(|argument| argument.split_whitespace());
+        let name = match words.next() {
+            Some(name) => name.to_owned(),
+            None => {
+                self.diagnostics
+                    .push(origin.diagnostic(span, ".macro takes a name"));
+                return;
+            }
+        };
+        let parameters = words
+            .map(|parameter| {
+                let (parameter, default) = match parameter.split_once('=') {
+                    Some((parameter, default)) => (parameter, Some(default.to_owned())),
+                    None => (parameter, None),
+                };
+                let (parameter, required) = match parameter.strip_suffix(":req") {
+                    Some(parameter) => (parameter, true),
+                    None => (parameter, false),
+                };
+                Parameter {
+                    name: parameter.to_owned(),
+                    required,
+                    default,
+                }
+            })
+            .collect();
+        self.defining = Some((
+            name,
+            origin.span(span),
+            Macro {
+                parameters,
+                body: Vec::new(),
+            },
+        ));
+    }
+
+    fn expand(
+        &mut self,
+        name: &str,
+        arguments: Vec<String>,
+        span: Span,
+        origin: Origin,
+        depth: usize,
+    ) {
+        let definition = match self.macros.get(name) {
+            Some(definition) => definition.clone(),
+            None => return,
+        };
+
+        if depth >= MAX_DEPTH {
+            self.diagnostics
+                .push(origin.diagnostic(span, format!("macro {} is expanded too deep", name)));
+            return;
+        }
+
+        if arguments.len() > definition.parameters.len() {
+            self.diagnostics.push(origin.diagnostic(
+                span,
+                format!(
+                    "macro {} takes {} arguments, not {}",
+                    name,
+                    definition.parameters.len(),
+                    arguments.len()
+                ),
+            ));
+            return;
+        }
+
+        let mut values = Vec::with_capacity(definition.parameters.len());
+        for (index, parameter) in definition.parameters.iter().enumerate() {
+            match arguments.get(index).or(parameter.default.as_ref()) {
+                Some(value) => values.push((parameter.name.as_str(), value.as_str())),
+                None if parameter.required => {
+                    self.diagnostics.push(origin.diagnostic(
+                        span,
+                        format!("macro {} is missing argument {}", name, parameter.name),
+                    ));
+                    return;
+                }
+                None => values.push((parameter.name.as_str(), "")),
             }
+        }
 
-            let message = Message {
-                name: name.to_owned(),
-                location,
-                text: message_pages,
-            };
+        // longer names first, so `\var` does not replace the start of `\var2`
+        values.sort_by_key(|(parameter, ..)| std::cmp::Reverse(parameter.len()));
 
-            messages.push(message);
+        let mut body = definition.body.join("\n");
+        for (parameter, value) in values {
+            body = body.replace(&format!("\\{}", parameter), value);
         }
+        let body = body.replace("\\()", "");
+
+        let origin = Origin {
+            file: origin.file,
+            expansion: Some(origin.span(span)),
+        };
+        self.source(&body, origin, depth + 1);
     }
-    Ok(messages)
 }
 
-pub type Location = u32;
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
 
-fn location(args: &mut std::str::SplitWhitespace, line: usize) -> Result<Option<Location>, Error> {
-    let at = args.next();
-    let location = if at
-        .map(|at| at.eq_ignore_ascii_case("@"))
-        .unwrap_or_default()
-    {
-        let location = args.next().ok_or(Error::ScriptLocation(line))?;
-        let location = Location::from_str_radix(location, 16)
-            .map_err(|err| Error::MalformedLocation(err, line))?;
-        Some(location)
-        // return Err(Error::ScriptName(line, ScriptNameReason::At));
-    } else {
-        None
-    };
-    Ok(location)
+fn arguments(source: &str, tokens: &[&Token]) -> Result<Vec<String>, Diagnostic> {
+    let mut arguments = Vec::new();
+    if tokens.is_empty() {
+        return Ok(arguments);
+    }
+    let mut depth = 0usize;
+    let mut start: Option<&Token> = None;
+    let mut end: Option<&Token> = None;
+    for token in tokens.iter().copied().chain(std::iter::once(&Token {
+        kind: TokenKind::Comma,
+        span: tokens[tokens.len() - 1].span,
+        range: 0..0,
+    })) {
+        match token.kind {
+            TokenKind::Comma if depth == 0 => {
+                let (start, end) = match (start.take(), end.take()) {
+                    (Some(start), Some(end)) => (start, end),
+                    _ => return Err(Diagnostic::new(token.span, "empty argument")),
+                };
+                arguments.push(source[start.range.start..end.range.end].to_owned());
+            }
+            _ => {
+                match token.kind {
+                    TokenKind::Punct('(') => depth += 1,
+                    TokenKind::Punct(')') => depth = depth.saturating_sub(1),
+                    _ => (),
+                }
+                start.get_or_insert(token);
+                end = Some(token);
+            }
+        }
+    }
+    Ok(arguments)
 }
 
+pub type Location = u32;
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Script {
     pub name: String,
     pub location: Option<Location>,
     pub commands: Vec<Command>,
+    /// Data defined after the label, such as the text of a message
+    #[serde(default)]
+    pub data: Vec<Data>,
+    /// Where the label is
+    #[serde(default)]
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Command {
     pub command: String,
     pub arguments: Vec<String>,
+    #[serde(default)]
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Message {
-    pub name: String,
-    pub location: Option<Location>,
-    pub text: Vec<Vec<String>>,
+/// Data directives, with the values as written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Data {
+    /// `.byte`
+    Byte(Vec<String>),
+    /// `.2byte` or `.hword`
+    Half(Vec<String>),
+    /// `.4byte` or `.word`
+    Word(Vec<String>),
+    /// Game text of `.string`, without quotes and with its escapes and control codes
+    String(String),
+    /// Text of `.braille`, without quotes
+    Braille(String),
+}
+
+/// A position in a source file, starting at line 1, column 1
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
 }
 
-#[derive(Debug)]
-pub enum Error {
-    SetName(usize),
-    SetVariable(usize),
-    ScriptName(usize, ScriptNameReason),
-    ScriptLocation(usize),
-    MalformedLocation(std::num::ParseIntError, usize),
-    NoCommand(usize),
-    NoArguments(usize),
-    EndOfFile(&'static str),
+/// An error found while parsing, after which parsing went on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
 }
 
-#[derive(Debug)]
-pub enum ScriptNameReason {
-    Name,
-    Location,
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
 }
 
-impl std::error::Error for Error {}
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
 
-impl std::fmt::Display for Error {
+/// Writes the command as it appears in a script, `command arg0, arg1`
+impl std::fmt::Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::SetName(line) => {
-                write!(f, "Could not get variable name for .set at line {}", line)
-            }
-            Error::SetVariable(line) => {
-                write!(f, "Could not get variable for .set at line {}", line)
+        f.write_str(&self.command)?;
+        if !self.arguments.is_empty() {
+            write!(f, " {}", self.arguments.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub name: String,
+    pub location: Option<Location>,
+    /// Lines as written, which [`crate::text::Charmap`] decodes
+    pub text: Vec<TextLine>,
+}
+
+impl Message {
+    /// Reads a parsed script as a text label, which it is if it has `.string` data and no commands
+    pub fn from_script(script: &Script) -> Option<Self> {
+        if !script.commands.is_empty() {
+            return None;
+        }
+        let mut text = String::new();
+        for data in script.data.iter() {
+            if let Data::String(string) = data {
+                text.push_str(string);
             }
-            Error::MalformedLocation(err, line) => write!(
-                f,
-                "Could not parse script location at line {} with error {}",
-                line, err
-            ),
-            _ => std::fmt::Debug::fmt(&self, f),
         }
+        if text.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: script.name.clone(),
+            location: script.location,
+            text: text::split_lines(&text),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Script, Span};
+
+    fn scripts(source: &str) -> Vec<Script> {
+        let (scripts, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        scripts
+    }
+
+    fn commands(script: &Script) -> Vec<String> {
+        script.commands.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn last_command_before_label() {
+        let scripts = scripts(
+            "Route1_EventScript_Boy::
+    lock
+    goto Route1_EventScript_Talk
+Route1_EventScript_Talk::
+    release
+    end
+",
+        );
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(
+            commands(&scripts[0]),
+            ["lock", "goto Route1_EventScript_Talk"]
+        );
+        assert_eq!(commands(&scripts[1]), ["release", "end"]);
+    }
+
+    #[test]
+    fn local_labels() {
+        let scripts = scripts(
+            "Route1_EventScript_Boy::
+    call Route1_EventScript_Greet
+    end
+Route1_EventScript_Greet:
+    msgbox Route1_Text_Hello
+    return
+",
+        );
+        assert_eq!(scripts[1].name, "Route1_EventScript_Greet");
+        assert_eq!(
+            commands(&scripts[1]),
+            ["msgbox Route1_Text_Hello", "return"]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        let scripts = scripts(
+            "Route1_EventScript_Boy:: @ 81A7ADB
+    @ talks to the player
+    msgbox Route1_Text_Hello, MSGBOX_NPC @ facing them
+    end
+",
+        );
+        assert_eq!(scripts[0].location, Some(0x81A7ADB));
+        assert_eq!(
+            commands(&scripts[0]),
+            ["msgbox Route1_Text_Hello, MSGBOX_NPC", "end"]
+        );
+    }
+
+    #[test]
+    fn separators() {
+        let scripts = scripts(
+            "Route1_EventScript_Boy::
+    lock; faceplayer; release
+    end
+",
+        );
+        assert_eq!(
+            commands(&scripts[0]),
+            ["lock", "faceplayer", "release", "end"]
+        );
+    }
+
+    #[test]
+    fn macro_defaults() {
+        let scripts = scripts(
+            ".macro giveitem item:req, amount=1
+    additem \\item, \\amount
+.endm
+Route1_EventScript_Potions::
+    giveitem ITEM_POTION
+    giveitem ITEM_POTION, 5
+",
+        );
+        assert_eq!(
+            commands(&scripts[0]),
+            ["additem ITEM_POTION, 1", "additem ITEM_POTION, 5"]
+        );
+    }
+
+    #[test]
+    fn error_recovery() {
+        let (scripts, diagnostics) = parse(
+            "Route1_EventScript_Boy::
+    lock
+    , faceplayer
+    msgbox Route1_Text_Hello,
+    release
+",
+        );
+        assert_eq!(commands(&scripts[0]), ["lock", "release"]);
+        assert_eq!(
+            diagnostics.iter().map(|d| d.span).collect::<Vec<_>>(),
+            [
+                Span { line: 3, column: 5 },
+                Span {
+                    line: 4,
+                    column: 29
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn includes() {
+        let (scripts, diagnostics) = super::parse_with(
+            "    .include \"data/scripts/pc.inc\"
+    .include \"data/scripts/missing.inc\"
+    .include \"asm/macros.inc\"
+",
+            |file| match file {
+                "data/scripts/pc.inc" => Ok(Some("EventScript_PC::\n    end\n".to_owned())),
+                "data/scripts/missing.inc" => Err("404 Not Found".to_owned()),
+                _ => Ok(None),
+            },
+        );
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].name, "EventScript_PC");
+        // only the file that could not be read is reported
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.line, 2);
+        assert!(diagnostics[0].message.contains("404 Not Found"));
     }
 }
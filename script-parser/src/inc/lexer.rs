@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use super::{Diagnostic, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind<'a> {
+    /// Names, including directives (`.set`) and macro parameters (`\var`)
+    Ident(&'a str),
+    Number(&'a str),
+    /// The contents of a string literal, with its escapes as written
+    Str(&'a str),
+    /// An `@` or `//` comment without its marker
+    Comment(&'a str),
+    Comma,
+    Colon,
+    DoubleColon,
+    /// Any other single character, such as the operators of an expression
+    Punct(char),
+    /// The end of a statement, which is a new line or `;`
+    Newline,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+    pub span: Span,
+    /// Byte range in the source
+    pub range: Range<usize>,
+}
+
+/// Splits event script source (GNU as syntax, after the C preprocessor) into tokens.
+///
+/// Block comments and `#` lines of the preprocessor are skipped,
+/// and every character that cannot start a token is reported and skipped.
+pub fn lex(source: &str) -> (Vec<Token<'_>>, Vec<Diagnostic>) {
+    let mut tokens = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    let mut chars = source.char_indices().peekable();
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut line_empty = true;
+
+    while let Some((start, c)) = chars.next() {
+        let span = Span {
+            line,
+            column: source[line_start..start].chars().count() + 1,
+        };
+
+        let mut take_while = |f: &dyn Fn(char) -> bool| {
+            let mut end = start + c.len_utf8();
+            while let Some((i, c)) = chars.peek().copied() {
+                if !f(c) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            end
+        };
+
+        let kind = match c {
+            '\n' | ';' => {
+                if c == '\n' {
+                    line += 1;
+                    line_start = start + 1;
+                }
+                line_empty = true;
+                tokens.push(Token {
+                    kind: TokenKind::Newline,
+                    span,
+                    range: start..start + 1,
+                });
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '#' if line_empty => {
+                take_while(&|c| c != '\n');
+                continue;
+            }
+            '@' => {
+                let end = take_while(&|c| c != '\n');
+                TokenKind::Comment(source[start + 1..end].trim())
+            }
+            '/' if source[start..].starts_with("//") => {
+                let end = take_while(&|c| c != '\n');
+                TokenKind::Comment(source[start + 2..end].trim())
+            }
+            '/' if source[start..].starts_with("/*") => {
+                let end = match source[start + 2..].find("*/") {
+                    Some(end) => start + 2 + end + 2,
+                    None => {
+                        diagnostics.push(Diagnostic::new(span, "unterminated block comment"));
+                        source.len()
+                    }
+                };
+                while let Some((i, c)) = chars.peek().copied() {
+                    if i >= end {
+                        break;
+                    }
+                    if c == '\n' {
+                        line += 1;
+                        line_start = i + 1;
+                    }
+                    chars.next();
+                }
+                continue;
+            }
+            '"' => {
+                let mut escaped = false;
+                let mut end = None;
+                for (i, c) in chars.by_ref() {
+                    match c {
+                        '\n' => break,
+                        '"' if !escaped => {
+                            end = Some(i);
+                            break;
+                        }
+                        '\\' => escaped = !escaped,
+                        _ => escaped = false,
+                    }
+                }
+                match end {
+                    Some(end) => TokenKind::Str(&source[start + 1..end]),
+                    None => {
+                        diagnostics.push(Diagnostic::new(span, "unterminated string"));
+                        // the new line was consumed with the string
+                        line += 1;
+                        line_start = source[start..]
+                            .find('\n')
+                            .map(|i| start + i + 1)
+                            .unwrap_or(source.len());
+                        line_empty = true;
+                        tokens.push(Token {
+                            kind: TokenKind::Newline,
+                            span,
+                            range: start..start,
+                        });
+                        continue;
+                    }
+                }
+            }
+            ',' => TokenKind::Comma,
+            ':' => match chars.peek() {
+                Some((.., ':')) => {
+                    chars.next();
+                    TokenKind::DoubleColon
+                }
+                _ => TokenKind::Colon,
+            },
+            c if c.is_ascii_digit() => {
+                let end = take_while(&|c| c.is_ascii_alphanumeric() || c == '_');
+                TokenKind::Number(&source[start..end])
+            }
+            c if is_ident(c) || c == '.' || c == '\\' => {
+                let end = take_while(&|c| is_ident(c) || c == '.');
+                TokenKind::Ident(&source[start..end])
+            }
+            c if c.is_ascii_punctuation() => TokenKind::Punct(c),
+            c => {
+                diagnostics.push(Diagnostic::new(
+                    span,
+                    format!("unexpected character {:?}", c),
+                ));
+                continue;
+            }
+        };
+
+        line_empty = false;
+
+        let end = chars.peek().map(|(i, ..)| *i).unwrap_or(source.len());
+
+        tokens.push(Token {
+            kind,
+            span,
+            range: start..end,
+        });
+    }
+
+    (tokens, diagnostics)
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -1,2 +1,7 @@
+pub mod constants;
 pub mod inc;
+pub mod menu;
+pub mod movement;
+pub mod names;
+pub mod text;
 pub mod trainer;
\ No newline at end of file
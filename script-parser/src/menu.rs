@@ -0,0 +1,72 @@
+use hashbrown::HashMap;
+
+/// Parses the multichoice lists of `script_menu.h` into the text symbols of their options, keyed by `MULTICHOICE_*` id
+///
+/// ```c
+/// static const struct MenuAction sMultichoiceList_YesNo[] = {
+///     {gText_Yes},
+///     {gText_No},
+/// };
+///
+/// static const struct MultichoiceListStruct sMultichoiceLists[] = {
+///     [MULTICHOICE_YES_NO] = MULTICHOICE(sMultichoiceList_YesNo),
+/// };
+/// ```
+pub fn parse_multichoices(file: &str) -> HashMap<String, Vec<String>> {
+    let mut lists = HashMap::new();
+    let mut ids = HashMap::new();
+    let mut current: Option<(String, Vec<String>)> = None;
+
+    for text in file.lines() {
+        let text = text.split_once("//").map(|(l, ..)| l).unwrap_or(text).trim();
+
+        if let Some((name, options)) = current.as_mut() {
+            if text.starts_with("};") {
+                lists.insert(std::mem::take(name), std::mem::take(options));
+                current = None;
+            } else if let Some(option) = text
+                .strip_prefix('{')
+                .and_then(|text| text.split([',', '}']).next())
+                .map(str::trim)
+                .filter(|option| !option.is_empty())
+            {
+                options.push(option.to_owned());
+            }
+            continue;
+        }
+
+        if text.contains("struct MenuAction") {
+            if let Some(name) = text
+                .split_once("[]")
+                .and_then(|(name, ..)| name.split_whitespace().last())
+            {
+                current = Some((name.to_owned(), Vec::new()));
+            }
+            continue;
+        }
+
+        // [MULTICHOICE_YES_NO] = MULTICHOICE(sMultichoiceList_YesNo),
+        // or [MULTICHOICE_YES_NO] = {sMultichoiceList_YesNo, ARRAY_COUNT(sMultichoiceList_YesNo)},
+        if let Some((id, list)) = text
+            .strip_prefix('[')
+            .and_then(|text| text.split_once(']'))
+            .filter(|(id, ..)| id.starts_with("MULTICHOICE_"))
+        {
+            let list = list
+                .trim_start_matches(|c: char| c.is_whitespace() || c == '=')
+                .trim_start_matches("MULTICHOICE")
+                .trim_start_matches(['(', '{']);
+            if let Some(list) = list
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .next()
+                .filter(|list| !list.is_empty())
+            {
+                ids.insert(id.trim().to_owned(), list.to_owned());
+            }
+        }
+    }
+
+    ids.into_iter()
+        .flat_map(|(id, list)| Some((id, lists.get(&list)?.clone())))
+        .collect()
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use crate::inc::Script;
+
+/// A movement data block, such as
+///
+/// ```text
+/// Movement_WalkToCounter::
+///     walk_up
+///     walk_up
+///     face_left
+///     step_end
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Movement {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Speed {
+    Slowest,
+    Slow,
+    Normal,
+    Fast,
+    Faster,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Step {
+    Walk(Direction, Speed),
+    /// Walking animation without moving
+    WalkInPlace(Direction, Speed),
+    Face(Direction),
+    FacePlayer,
+    FaceAwayPlayer,
+    /// Jumps a number of tiles in a direction, where 0 is jumping in place
+    Jump(Direction, u8),
+    /// Waits for a number of frames
+    Delay(u16),
+    SetVisible(bool),
+    /// Shows an emote such as `exclamation_mark` above the object
+    Emote(String),
+    /// A movement macro without a step here, such as `lock_facing_direction`
+    Other(String),
+}
+
+/// Terminator of every movement block
+pub const STEP_END: &str = "step_end";
+
+impl Movement {
+    /// Reads a parsed script as a movement block,
+    /// which it is if its commands take no arguments and end with [`STEP_END`].
+    ///
+    /// Macros [`Step::parse`] does not know are kept as [`Step::Other`], see [`Movement::unknown`].
+    pub fn from_script(script: &Script) -> Option<Self> {
+        let mut steps = Vec::with_capacity(script.commands.len());
+        for command in script.commands.iter() {
+            if !command.arguments.is_empty() {
+                return None;
+            }
+            match command.command.as_str() {
+                STEP_END => {
+                    return Some(Self {
+                        name: script.name.clone(),
+                        steps,
+                    })
+                }
+                step => {
+                    steps.push(Step::parse(step).unwrap_or_else(|| Step::Other(step.to_owned())))
+                }
+            }
+        }
+        None
+    }
+
+    /// The macros of the block without a step
+    pub fn unknown(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Other(step) => Some(step.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl Step {
+    /// Parses a movement macro such as `walk_fast_left` or `delay_16`
+    pub fn parse(step: &str) -> Option<Self> {
+        if let Some(frames) = step.strip_prefix("delay_") {
+            return frames.parse().ok().map(Step::Delay);
+        }
+        if let Some(emote) = step.strip_prefix("emote_") {
+            return Some(Step::Emote(emote.to_owned()));
+        }
+        match step {
+            "face_player" => return Some(Step::FacePlayer),
+            "face_away_player" => return Some(Step::FaceAwayPlayer),
+            "set_visible" => return Some(Step::SetVisible(true)),
+            "set_invisible" => return Some(Step::SetVisible(false)),
+            _ => (),
+        }
+
+        let (kind, direction) = step.rsplit_once('_')?;
+        let direction = match direction {
+            "down" => Direction::Down,
+            "up" => Direction::Up,
+            "left" => Direction::Left,
+            "right" => Direction::Right,
+            _ => return None,
+        };
+
+        Some(match kind {
+            "face" => Step::Face(direction),
+            "walk" => Step::Walk(direction, Speed::Normal),
+            "walk_slowest" => Step::Walk(direction, Speed::Slowest),
+            "walk_slow" => Step::Walk(direction, Speed::Slow),
+            "walk_fast" | "player_run" => Step::Walk(direction, Speed::Fast),
+            "walk_faster" | "slide" => Step::Walk(direction, Speed::Faster),
+            "walk_in_place_slow" => Step::WalkInPlace(direction, Speed::Slow),
+            "walk_in_place" => Step::WalkInPlace(direction, Speed::Normal),
+            "walk_in_place_fast" => Step::WalkInPlace(direction, Speed::Fast),
+            "walk_in_place_faster" => Step::WalkInPlace(direction, Speed::Faster),
+            "jump_in_place" => Step::Jump(direction, 0),
+            "jump" => Step::Jump(direction, 1),
+            "jump_2" => Step::Jump(direction, 2),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Movement, Speed, Step};
+    use crate::inc;
+
+    fn movements(source: &str) -> Vec<Option<Movement>> {
+        let (scripts, diagnostics) = inc::parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        scripts.iter().map(Movement::from_script).collect()
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(
+            Step::parse("walk_slowest_up"),
+            Some(Step::Walk(Direction::Up, Speed::Slowest))
+        );
+        assert_eq!(
+            Step::parse("walk_in_place_fast_left"),
+            Some(Step::WalkInPlace(Direction::Left, Speed::Fast))
+        );
+        assert_eq!(
+            Step::parse("jump_2_down"),
+            Some(Step::Jump(Direction::Down, 2))
+        );
+        assert_eq!(Step::parse("delay_16"), Some(Step::Delay(16)));
+        assert_eq!(
+            Step::parse("emote_exclamation_mark"),
+            Some(Step::Emote("exclamation_mark".to_owned()))
+        );
+        for step in [
+            "lock_facing_direction",
+            "unlock_facing_direction",
+            "jump_special_down",
+            "set_fixed_priority",
+            "disable_anim",
+        ] {
+            assert_eq!(Step::parse(step), None, "{}", step);
+        }
+    }
+
+    #[test]
+    fn firered_movements() {
+        let movements = movements(
+            "PalletTown_ProfessorOaksLab_Movement_RivalPushed::
+    lock_facing_direction
+    walk_slowest_left
+    unlock_facing_direction
+    step_end
+
+Route4_Movement_JumpLedge::
+    jump_special_down
+    step_end
+
+SSAnne_Exterior_Movement_ShipDeparts::
+    set_fixed_priority
+    disable_anim
+    walk_slowest_right
+    delay_16
+    step_end
+
+PalletTown_Movement_Empty::
+    step_end
+
+PalletTown_EventScript_Sign::
+    lock
+    faceplayer
+    release
+    end
+",
+        );
+
+        let rival = movements[0].as_ref().unwrap();
+        assert_eq!(
+            rival.steps,
+            [
+                Step::Other("lock_facing_direction".to_owned()),
+                Step::Walk(Direction::Left, Speed::Slowest),
+                Step::Other("unlock_facing_direction".to_owned()),
+            ]
+        );
+        assert_eq!(
+            rival.unknown().collect::<Vec<_>>(),
+            ["lock_facing_direction", "unlock_facing_direction"]
+        );
+
+        let ledge = movements[1].as_ref().unwrap();
+        assert_eq!(ledge.unknown().collect::<Vec<_>>(), ["jump_special_down"]);
+
+        let ship = movements[2].as_ref().unwrap();
+        assert_eq!(
+            ship.steps[2..],
+            [
+                Step::Walk(Direction::Right, Speed::Slowest),
+                Step::Delay(16)
+            ]
+        );
+        assert_eq!(
+            ship.unknown().collect::<Vec<_>>(),
+            ["set_fixed_priority", "disable_anim"]
+        );
+
+        assert!(movements[3].as_ref().unwrap().steps.is_empty());
+        // an event script has no step_end
+        assert!(movements[4].is_none());
+    }
+}
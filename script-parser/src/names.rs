@@ -0,0 +1,55 @@
+use hashbrown::HashMap;
+
+/// Parses a designated-initializer name table such as `gSpeciesNames`
+///
+/// `[SPECIES_BULBASAUR] = _("BULBASAUR"),` becomes `SPECIES_BULBASAUR` -> `BULBASAUR`.
+pub fn parse_names(file: &str) -> HashMap<String, String> {
+    file.lines()
+        .flat_map(|text| {
+            let text = text.trim();
+            let (key, value) = text.strip_prefix('[')?.split_once(']')?;
+            let value = value.trim().strip_prefix('=')?.trim();
+            let (.., value) = value.split_once("_(\"")?;
+            let (value, ..) = value.rsplit_once("\")")?;
+            Some((key.trim().to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+/// Parses the strings of a C file such as `strings.c`
+///
+/// `const u8 gText_Yes[] = _("YES");` becomes `gText_Yes` -> `YES`,
+/// where strings split across lines (`_("ONE\n"\n    "TWO")`) are joined.
+pub fn parse_strings(file: &str) -> HashMap<String, String> {
+    let mut strings = HashMap::new();
+    let mut lines = file.lines();
+
+    while let Some(text) = lines.next() {
+        let (name, value) = match text.split_once("[] = _(") {
+            Some((name, value)) => (name, value),
+            None => continue,
+        };
+        let name = match name.split_whitespace().last() {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+
+        let mut value = value.to_owned();
+        while !value.trim_end().ends_with(';') {
+            match lines.next() {
+                Some(next) => value.push_str(next),
+                None => break,
+            }
+        }
+
+        let string = value
+            .split('"')
+            .skip(1)
+            .step_by(2)
+            .collect::<String>();
+
+        strings.insert(name, string);
+    }
+
+    strings
+}
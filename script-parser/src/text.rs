@@ -0,0 +1,551 @@
+use hashbrown::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A line of decoded game text
+pub type Line = Vec<Segment>;
+
+/// A run of game text, as the game's text printer reads it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Segment {
+    Text(String),
+    /// A string the game fills in, such as `PLAYER`, `RIVAL` or `STR_VAR_1`
+    Variable(String),
+    /// Sets a text colour (`COLOR`, `HIGHLIGHT`, `SHADOW`) to the named colours
+    Color(String, Vec<String>),
+    /// Waits a number of frames
+    Pause(u8),
+    /// Waits for a button press without a new page
+    PauseUntilPress,
+    /// Plays a sound effect (`PLAY_SE`) or song (`PLAY_BGM`)
+    Sound(String),
+    /// A character without text of its own, such as the button glyphs
+    Glyph(String),
+    /// Any other control code, with its arguments as written
+    Control(String, Vec<String>),
+}
+
+/// How a line of game text ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LineBreak {
+    /// `\n`, the next line is printed below
+    NewLine,
+    /// `\l`, the text box scrolls up for the next line
+    Scroll,
+    /// `\p`, the next line starts a new page after a button press
+    Page,
+    /// `$`, or the end of text without one
+    End,
+}
+
+/// A line of game text with the break that ends it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextLine<T = String> {
+    pub text: T,
+    pub end: LineBreak,
+}
+
+impl<T> TextLine<T> {
+    pub fn map<U>(&self, f: impl FnOnce(&T) -> U) -> TextLine<U> {
+        TextLine {
+            text: f(&self.text),
+            end: self.end,
+        }
+    }
+}
+
+/// Splits game text at its `\n`, `\l` and `\p` breaks, up to the `$` terminator.
+///
+/// Other escapes are kept for [`Charmap::decode`].
+pub fn split_lines(text: &str) -> Vec<TextLine> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        let end = match c {
+            '$' => LineBreak::End,
+            '\\' => match chars.next() {
+                Some('n') => LineBreak::NewLine,
+                Some('l') => LineBreak::Scroll,
+                Some('p') => LineBreak::Page,
+                escaped => {
+                    line.push('\\');
+                    line.extend(escaped);
+                    continue;
+                }
+            },
+            c => {
+                line.push(c);
+                continue;
+            }
+        };
+        lines.push(TextLine {
+            text: std::mem::take(&mut line),
+            end,
+        });
+        if end == LineBreak::End {
+            return lines;
+        }
+    }
+
+    // text without a `$` terminator
+    if !line.is_empty() {
+        lines.push(TextLine {
+            text: line,
+            end: LineBreak::End,
+        });
+    }
+
+    lines
+}
+
+/// Groups lines into the pages they are shown on, where scrolled lines stay on their page
+pub fn pages<T>(lines: &[TextLine<T>]) -> Vec<Vec<&T>> {
+    let mut pages = Vec::new();
+    let mut page = Vec::new();
+    for line in lines {
+        page.push(&line.text);
+        if line.end == LineBreak::Page {
+            pages.push(std::mem::take(&mut page));
+        }
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+const PLACEHOLDER: u8 = 0xFD;
+const CONTROL: u8 = 0xFC;
+
+/// The encoding of the games' text, parsed from `charmap.txt`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Charmap {
+    /// Characters, `'A' = BB`
+    pub characters: HashMap<char, Vec<u8>>,
+    /// Names used in braces, `PLAYER = FD 01`
+    pub codes: HashMap<String, Vec<u8>>,
+}
+
+impl Charmap {
+    /// Reads `'c' = XX` and `NAME = XX XX` lines, skipping `@` comments and every line it cannot read
+    pub fn parse(file: &str) -> Self {
+        let mut charmap = Self::default();
+
+        for line in file.lines() {
+            let line = line.trim();
+            if line.starts_with('@') {
+                continue;
+            }
+
+            // the last `=`, since `'=' = 35` is a character too
+            let (key, value) = match line.rsplit_once('=') {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let value = value.split('@').next().unwrap_or_default();
+            let bytes = match value
+                .split_whitespace()
+                .map(|byte| u8::from_str_radix(byte, 16))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(bytes) if !bytes.is_empty() => bytes,
+                _ => continue,
+            };
+
+            let key = key.trim();
+            match key
+                .strip_prefix('\'')
+                .and_then(|key| key.strip_suffix('\''))
+            {
+                Some(character) => {
+                    let mut chars = character.chars();
+                    let character = match (chars.next(), chars.next(), chars.next()) {
+                        // the terminators are read with pagination
+                        (Some('\\'), Some('n' | 'l' | 'p'), None) => continue,
+                        (Some('\\'), Some(escaped), None) => escaped,
+                        (Some(character), None, ..) => character,
+                        _ => continue,
+                    };
+                    charmap.characters.insert(character, bytes);
+                }
+                None if !key.is_empty() => {
+                    charmap.codes.insert(key.to_owned(), bytes);
+                }
+                None => (),
+            }
+        }
+
+        charmap
+    }
+
+    /// Decodes a line of game text, without its `\n`, `\l`, `\p` or `$` break, see [`split_lines`],
+    /// along with the characters the charmap does not have.
+    ///
+    /// Names in braces that are not in the charmap become [`Segment::Control`].
+    pub fn decode(&self, text: &str) -> (Line, Vec<char>) {
+        let mut segments = Vec::new();
+        let mut run = String::new();
+        let mut missing = Vec::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let code = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                    let mut words = code.split_whitespace();
+                    let name = match words.next() {
+                        Some(name) => name,
+                        None => continue,
+                    };
+                    let arguments = words.map(str::to_owned).collect::<Vec<_>>();
+                    match self.code(name, arguments) {
+                        Segment::Text(text) => run.push_str(&text),
+                        segment => {
+                            if !run.is_empty() {
+                                segments.push(Segment::Text(std::mem::take(&mut run)));
+                            }
+                            segments.push(segment);
+                        }
+                    }
+                }
+                c => {
+                    let c = match c {
+                        '\\' => match chars.next() {
+                            Some(escaped) => escaped,
+                            None => continue,
+                        },
+                        c => c,
+                    };
+                    if !self.characters.contains_key(&c) && !missing.contains(&c) {
+                        missing.push(c);
+                    }
+                    run.push(c);
+                }
+            }
+        }
+
+        if !run.is_empty() {
+            segments.push(Segment::Text(run));
+        }
+
+        (segments, missing)
+    }
+
+    /// Encodes text into the games' bytes, without a terminator,
+    /// along with the characters and names in braces the charmap does not have, which are left out.
+    ///
+    /// The arguments of a name in braces are names too, or numbers.
+    pub fn encode(&self, text: &str) -> (Vec<u8>, Vec<String>) {
+        let mut bytes = Vec::new();
+        let mut unknown = Vec::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            let c = match c {
+                '{' => {
+                    let code = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                    for word in code.split_whitespace() {
+                        match self.codes.get(word) {
+                            Some(code) => bytes.extend_from_slice(code),
+                            None => match parse_int(word) {
+                                Some(byte) => bytes.push(byte),
+                                None => unknown.push(word.to_owned()),
+                            },
+                        }
+                    }
+                    continue;
+                }
+                '\\' => match chars.next() {
+                    Some(escaped) => escaped,
+                    None => continue,
+                },
+                c => c,
+            };
+            match self.characters.get(&c) {
+                Some(character) => bytes.extend_from_slice(character),
+                None => unknown.push(c.to_string()),
+            }
+        }
+
+        (bytes, unknown)
+    }
+
+    fn code(&self, name: &str, mut arguments: Vec<String>) -> Segment {
+        let bytes = match self.codes.get(name) {
+            Some(bytes) => bytes.as_slice(),
+            None => return Segment::Control(name.to_owned(), arguments),
+        };
+        match bytes {
+            [PLACEHOLDER, ..] => Segment::Variable(name.to_owned()),
+            [CONTROL, 0x01..=0x04, ..] => Segment::Color(name.to_owned(), arguments),
+            [CONTROL, 0x08, ..] => match arguments.as_slice() {
+                [frames] => match parse_int(frames) {
+                    Some(frames) => Segment::Pause(frames),
+                    None => Segment::Control(name.to_owned(), arguments),
+                },
+                _ => Segment::Control(name.to_owned(), arguments),
+            },
+            [CONTROL, 0x09, ..] => Segment::PauseUntilPress,
+            [CONTROL, 0x0B | 0x10, ..] if arguments.len() == 1 => {
+                Segment::Sound(arguments.remove(0))
+            }
+            [CONTROL, ..] => Segment::Control(name.to_owned(), arguments),
+            // a name for bytes that are also a character
+            bytes => match self
+                .characters
+                .iter()
+                .find(|(.., character)| character.as_slice() == bytes)
+            {
+                Some((character, ..)) if arguments.is_empty() => {
+                    Segment::Text(character.to_string())
+                }
+                _ => Segment::Glyph(name.to_owned()),
+            },
+        }
+    }
+}
+
+fn parse_int(arg: &str) -> Option<u8> {
+    match arg.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pages, split_lines, Charmap, LineBreak, Segment, TextLine};
+    use crate::inc::{self, Message};
+
+    /// Lines of `charmap.txt`, with the letters added by [`charmap`]
+    const CHARMAP: &str = "@ characters
+' ' = 00
+'é' = 1B
+'&' = 2D
+'(' = 5C
+')' = 5D
+'!' = AB
+'\\'' = B4
+'\\n' = FE
+
+@ codes
+PLAYER = FD 01
+COLOR = FC 01
+PAUSE = FC 08
+PAUSE_UNTIL_PRESS = FC 09
+PLAY_BGM = FC 0B
+PLAY_SE = FC 10
+RED = 04
+LV = 34
+";
+
+    fn charmap() -> Charmap {
+        let mut charmap = CHARMAP.to_owned();
+        for (letter, byte) in ('A'..='Z').zip(0xBB..).chain(('a'..='z').zip(0xD5..)) {
+            charmap.push_str(&format!("'{}' = {:02X}\n", letter, byte));
+        }
+        Charmap::parse(&charmap)
+    }
+
+    fn line(text: &str, end: LineBreak) -> TextLine {
+        TextLine {
+            text: text.to_owned(),
+            end,
+        }
+    }
+
+    fn message(source: &str) -> Message {
+        let (scripts, diagnostics) = inc::parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        Message::from_script(&scripts[0]).unwrap()
+    }
+
+    #[test]
+    fn new_line() {
+        let message = message(
+            "PalletTown_Text_HeyWaitDontGoOut::
+    .string \"OAK: Hey! Wait!\\n\"
+    .string \"Don't go out!$\"
+",
+        );
+        assert_eq!(
+            message.text,
+            [
+                line("OAK: Hey! Wait!", LineBreak::NewLine),
+                line("Don't go out!", LineBreak::End),
+            ]
+        );
+        assert_eq!(pages(&message.text).len(), 1);
+    }
+
+    #[test]
+    fn scroll_and_page() {
+        let message = message(
+            "PalletTown_Text_OakGrassUnsafeNeedMon::
+    .string \"OAK: It's unsafe!\\n\"
+    .string \"Wild POKéMON live in tall grass!\\p\"
+    .string \"You need your own POKéMON for\\n\"
+    .string \"your protection.\\l\"
+    .string \"I know!\\n\"
+    .string \"Here, come with me!$\"
+",
+        );
+        assert_eq!(
+            message.text,
+            [
+                line("OAK: It's unsafe!", LineBreak::NewLine),
+                line("Wild POKéMON live in tall grass!", LineBreak::Page),
+                line("You need your own POKéMON for", LineBreak::NewLine),
+                line("your protection.", LineBreak::Scroll),
+                line("I know!", LineBreak::NewLine),
+                line("Here, come with me!", LineBreak::End),
+            ]
+        );
+        assert_eq!(
+            pages(&message.text),
+            [
+                vec!["OAK: It's unsafe!", "Wild POKéMON live in tall grass!"],
+                vec![
+                    "You need your own POKéMON for",
+                    "your protection.",
+                    "I know!",
+                    "Here, come with me!"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn breaks_within_a_string() {
+        // a line may be split across `.string`s and a `.string` may hold several breaks
+        let message = message(
+            "Text_WelcomeToPokeCenter::
+    .string \"Welcome to our \"
+    .string \"POKéMON CENTER!\\p\"
+    .string \"Would you like me to heal your\\nPOKéMON back to perfect health?$\"
+",
+        );
+        assert_eq!(
+            message.text,
+            [
+                line("Welcome to our POKéMON CENTER!", LineBreak::Page),
+                line("Would you like me to heal your", LineBreak::NewLine),
+                line("POKéMON back to perfect health?", LineBreak::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn terminator() {
+        assert_eq!(
+            split_lines("{PLAYER} received\\nthe {STR_VAR_2}!$ignored"),
+            [
+                line("{PLAYER} received", LineBreak::NewLine),
+                line("the {STR_VAR_2}!", LineBreak::End),
+            ]
+        );
+        // text without `$` still ends
+        assert_eq!(split_lines("Hello\\p"), [line("Hello", LineBreak::Page)]);
+        assert_eq!(split_lines("Hi"), [line("Hi", LineBreak::End)]);
+    }
+
+    #[test]
+    fn other_escapes() {
+        assert_eq!(
+            split_lines("\\\"Quoted\\\"$"),
+            [line("\\\"Quoted\\\"", LineBreak::End)]
+        );
+    }
+
+    #[test]
+    fn encode() {
+        let charmap = charmap();
+        assert_eq!(charmap.encode("BEN"), (vec![0xBC, 0xBF, 0xC8], vec![]));
+        // punctuation of names such as `B&W` or `(R)` is kept
+        assert_eq!(charmap.encode("A&(B)").0, [0xBB, 0x2D, 0x5C, 0xBC, 0x5D]);
+        assert_eq!(charmap.encode("\\'!").0, [0xB4, 0xAB]);
+        assert_eq!(
+            charmap.encode("{PLAYER}{COLOR RED}{PAUSE 0x10}").0,
+            [0xFD, 0x01, 0xFC, 0x01, 0x04, 0xFC, 0x08, 0x10]
+        );
+        assert_eq!(
+            charmap.encode("Aß{UNKNOWN}"),
+            (vec![0xBB], vec!["ß".to_owned(), "UNKNOWN".to_owned()])
+        );
+    }
+
+    fn text(text: &str) -> Segment {
+        Segment::Text(text.to_owned())
+    }
+
+    #[test]
+    fn decode() {
+        let charmap = charmap();
+        assert_eq!(
+            charmap.decode("Hi {PLAYER}!"),
+            (
+                vec![
+                    text("Hi "),
+                    Segment::Variable("PLAYER".to_owned()),
+                    text("!")
+                ],
+                vec![]
+            )
+        );
+        assert_eq!(
+            charmap
+                .decode("{COLOR RED}Hey{PAUSE 30}{PAUSE_UNTIL_PRESS}")
+                .0,
+            [
+                Segment::Color("COLOR".to_owned(), vec!["RED".to_owned()]),
+                text("Hey"),
+                Segment::Pause(30),
+                Segment::PauseUntilPress,
+            ]
+        );
+        assert_eq!(
+            charmap
+                .decode("{PLAY_SE SE_DING_DONG}{PLAY_BGM MUS_FANFARE}")
+                .0,
+            [
+                Segment::Sound("SE_DING_DONG".to_owned()),
+                Segment::Sound("MUS_FANFARE".to_owned()),
+            ]
+        );
+        // a name for bytes that are no character is a glyph
+        assert_eq!(charmap.decode("{LV}").0, [Segment::Glyph("LV".to_owned())]);
+        assert_eq!(
+            charmap.decode("{UNKNOWN_CODE 1 2}").0,
+            [Segment::Control(
+                "UNKNOWN_CODE".to_owned(),
+                vec!["1".to_owned(), "2".to_owned()]
+            )]
+        );
+        // a pause without frames is a control code
+        assert_eq!(
+            charmap.decode("{PAUSE}").0,
+            [Segment::Control("PAUSE".to_owned(), vec![])]
+        );
+    }
+
+    #[test]
+    fn decode_escapes() {
+        let charmap = charmap();
+        assert_eq!(
+            charmap.decode("It\\'s Ben"),
+            (vec![text("It's Ben")], vec![])
+        );
+        assert_eq!(charmap.decode("\\{A}"), (vec![text("{A}")], vec!['{', '}']));
+    }
+
+    #[test]
+    fn missing_characters() {
+        let (line, missing) = charmap().decode("Füße & Ü{PLAYER}ü");
+        assert_eq!(line[0], text("Füße & Ü"));
+        // every character once, in the order they are found
+        assert_eq!(missing, ['ü', 'ß', 'Ü']);
+    }
+}
@@ -5,6 +5,8 @@ use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 
 pub mod party;
+pub mod party_file;
+pub mod rematch;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Trainer {
@@ -31,7 +33,7 @@ pub fn parse_trainers(file: &str) -> Result<HashMap<String, Trainer>, TrainerErr
 
     let mut trainers = HashMap::new();
 
-    while let Some((line, text)) = lines.next() {
+    for (line, text) in lines.by_ref() {
         match text.trim() {
             "};" => break,
             _ => match id.is_some() {
@@ -39,11 +41,11 @@ pub fn parse_trainers(file: &str) -> Result<HashMap<String, Trainer>, TrainerErr
                     let (lb, ..) = text
                         .char_indices()
                         .find(|(.., c)| c == &'[')
-                        .ok_or_else(|| TrainerError::BracketParse(line, "id"))?;
+                        .ok_or(TrainerError::BracketParse(line, "id"))?;
                     let (rb, ..) = text
                         .char_indices()
                         .find(|(.., c)| c == &']')
-                        .ok_or_else(|| TrainerError::BracketParse(line, "id"))?;
+                        .ok_or(TrainerError::BracketParse(line, "id"))?;
                     id = Some(text[lb + 1..rb].to_owned());
                 }
                 true => match text.trim() {
@@ -72,7 +74,7 @@ pub fn parse_trainers(file: &str) -> Result<HashMap<String, Trainer>, TrainerErr
                                 let name = split.next().ok_or_else(|| {
                                     TrainerError::FieldParse(line, right.to_owned())
                                 })?;
-                                if name.len() != 0 {
+                                if !name.is_empty() {
                                     trainer.name = Some(name.to_owned());
                                 }
                             }
@@ -81,11 +83,11 @@ pub fn parse_trainers(file: &str) -> Result<HashMap<String, Trainer>, TrainerErr
                                 let (lb, ..) = right
                                     .char_indices()
                                     .find(|(.., c)| c == &'{')
-                                    .ok_or_else(|| TrainerError::BracketParse(line, "items"))?;
+                                    .ok_or(TrainerError::BracketParse(line, "items"))?;
                                 let (rb, ..) = right
                                     .char_indices()
                                     .find(|(.., c)| c == &'}')
-                                    .ok_or_else(|| TrainerError::BracketParse(line, "items"))?;
+                                    .ok_or(TrainerError::BracketParse(line, "items"))?;
                                 let array = &right[lb + 1..rb];
                                 trainer.items = array.split(',').map(str::to_owned).collect();
                             }
@@ -100,19 +102,29 @@ pub fn parse_trainers(file: &str) -> Result<HashMap<String, Trainer>, TrainerErr
                                     right.split('|').map(str::trim).map(str::to_owned).collect()
                             }
                             ".partySize" => (),
+                            // pokeemerald-expansion
+                            ".party" if right.starts_with("TRAINER_PARTY(") => {
+                                let (.., id) = right
+                                    .split_once('(')
+                                    .ok_or(TrainerError::BracketParse(line, "party"))?;
+                                let (id, ..) = id
+                                    .rsplit_once(')')
+                                    .ok_or(TrainerError::BracketParse(line, "party"))?;
+                                trainer.party = id.trim().to_owned();
+                            }
                             ".party" => {
-                                let (lb, ..) =
-                                    right.char_indices().find(|(.., c)| c == &'{').ok_or_else(
-                                        || TrainerError::BracketParse(line, "party (left"),
-                                    )?;
+                                let (lb, ..) = right
+                                    .char_indices()
+                                    .find(|(.., c)| c == &'{')
+                                    .ok_or(TrainerError::BracketParse(line, "party (left"))?;
                                 let rb = right
                                     .char_indices()
                                     .find(|(.., c)| c == &'}')
-                                    .ok_or_else(|| TrainerError::BracketParse(line, "party"))
+                                    .ok_or(TrainerError::BracketParse(line, "party"))
                                     .map(|(rb, ..)| rb)
                                     .unwrap_or(right.len());
                                 let party = &right[lb + 1..rb];
-                                if party.len() != 0 {
+                                if !party.is_empty() {
                                     let (.., id) = party.split_once('=').ok_or_else(|| {
                                         TrainerError::FieldParse(line, party.to_owned())
                                     })?;
@@ -181,3 +193,52 @@ impl std::fmt::Display for TrainerError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_trainers,
+        party::{parse_parties, Ivs, Stats},
+    };
+
+    #[test]
+    fn trainer_party() {
+        let trainers = parse_trainers(
+            "const struct Trainer gTrainers[] = {
+    [TRAINER_SAWYER_1] = {
+        .trainerClass = TRAINER_CLASS_HIKER,
+        .trainerPic = TRAINER_PIC_HIKER,
+        .trainerName = _(\"SAWYER\"),
+        .items = {},
+        .doubleBattle = FALSE,
+        .aiFlags = AI_FLAG_CHECK_BAD_MOVE,
+        .party = TRAINER_PARTY(sParty_Sawyer1),
+    },
+};
+",
+        )
+        .unwrap();
+        assert_eq!(trainers["TRAINER_SAWYER_1"].party, "sParty_Sawyer1");
+
+        let parties = parse_parties(
+            "static const struct TrainerMon sParty_Sawyer1[] = {
+    {
+    .lvl = 21,
+    .species = SPECIES_GEODUDE,
+    .iv = TRAINER_PARTY_IVS(31, 31, 31, 31, 31, 31),
+    .nature = NATURE_ADAMANT,
+    .isShiny = TRUE,
+    }
+};
+",
+        )
+        .unwrap();
+        let party = &parties["sParty_Sawyer1"];
+        assert_eq!(party.len(), 1);
+        assert_eq!(party[0].species, "SPECIES_GEODUDE");
+        assert_eq!(party[0].level, 21);
+        assert_eq!(party[0].ivs, Ivs::Stats(Stats::uniform(31)));
+        assert_eq!(party[0].nature.as_deref(), Some("NATURE_ADAMANT"));
+        assert!(party[0].shiny);
+    }
+}
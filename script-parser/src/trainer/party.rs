@@ -4,13 +4,92 @@ use serde::{Deserialize, Serialize};
 
 use super::TrainerError;
 
+pub type Parties = HashMap<String, Vec<TrainerPokemon>>;
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TrainerPokemon {
-    pub ivs: u8,
+    pub ivs: Ivs,
     pub level: u8,
     pub species: String,
     pub moves: Option<Vec<String>>,
     pub item: Option<String>,
+    pub evs: Option<Stats>,
+    /// `NATURE_*`
+    pub nature: Option<String>,
+    /// `ABILITY_*`
+    pub ability: Option<String>,
+    pub nickname: Option<String>,
+    /// `ITEM_*` of the ball the pokemon is in
+    pub ball: Option<String>,
+    /// `TRAINER_MON_MALE` or `TRAINER_MON_FEMALE`
+    pub gender: Option<String>,
+    pub shiny: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ivs {
+    /// 0-255 value scaled down to the same IV for every stat (`.iv = 255`)
+    Scaled(u8),
+    /// IV of every stat (`.iv = TRAINER_PARTY_IVS(...)`)
+    Stats(Stats),
+}
+
+impl Default for Ivs {
+    fn default() -> Self {
+        Self::Scaled(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    pub hp: u8,
+    pub attack: u8,
+    pub defense: u8,
+    pub speed: u8,
+    pub sp_attack: u8,
+    pub sp_defense: u8,
+}
+
+impl Stats {
+    pub const fn uniform(value: u8) -> Self {
+        Self {
+            hp: value,
+            attack: value,
+            defense: value,
+            speed: value,
+            sp_attack: value,
+            sp_defense: value,
+        }
+    }
+
+    /// Parses `TRAINER_PARTY_IVS(hp, atk, def, speed, spatk, spdef)` and its EV counterpart
+    fn parse(line: usize, field: &'static str, text: &str) -> Result<Self, TrainerError> {
+        let (.., args) = text
+            .split_once('(')
+            .ok_or(TrainerError::BracketParse(line, field))?;
+        let (args, ..) = args
+            .rsplit_once(')')
+            .ok_or(TrainerError::BracketParse(line, field))?;
+        let mut values = args.split(',').map(|value| {
+            value
+                .trim()
+                .parse::<u8>()
+                .map_err(|err| TrainerError::NumParse(line, field, err))
+        });
+        let mut next = || {
+            values
+                .next()
+                .unwrap_or_else(|| Err(TrainerError::FieldParse(line, text.to_owned())))
+        };
+        Ok(Self {
+            hp: next()?,
+            attack: next()?,
+            defense: next()?,
+            speed: next()?,
+            sp_attack: next()?,
+            sp_defense: next()?,
+        })
+    }
 }
 
 enum State {
@@ -95,7 +174,7 @@ pub fn parse_parties(file: &str) -> Result<HashMap<String, Vec<TrainerPokemon>>,
 
     // let mut previous = *lines.peek().map(|(.., s)| s).unwrap();
 
-    while let Some((line, text)) = lines.next() {
+    for (line, text) in lines.by_ref() {
         let text = text.split_once("//").map(|(l, ..)| l).unwrap_or(text);
 
         match state {
@@ -235,13 +314,19 @@ fn pokemon(
                 .split_once('=')
                 .ok_or_else(|| TrainerError::FieldParse(line, text.to_owned()))?;
             let (left, right) = (left.trim(), right.trim());
-            let right = &right[..right.len() - 1];
+            let right = right.strip_suffix(',').unwrap_or(right).trim();
             match left {
                 ".iv" => {
-                    pokemon.ivs = right
-                        .parse()
-                        .map_err(|err| TrainerError::NumParse(line, "ivs", err))?
+                    pokemon.ivs = match right.starts_with("TRAINER_PARTY_IVS") {
+                        true => Ivs::Stats(Stats::parse(line, "ivs", right)?),
+                        false => Ivs::Scaled(
+                            right
+                                .parse()
+                                .map_err(|err| TrainerError::NumParse(line, "ivs", err))?,
+                        ),
+                    }
                 }
+                ".ev" => pokemon.evs = Some(Stats::parse(line, "evs", right)?),
                 ".lvl" => {
                     pokemon.level = right
                         .parse()
@@ -252,16 +337,29 @@ fn pokemon(
                     let (lb, ..) = right
                         .char_indices()
                         .find(|(.., c)| c == &'{')
-                        .ok_or_else(|| TrainerError::BracketParse(line, "moves"))?;
+                        .ok_or(TrainerError::BracketParse(line, "moves"))?;
                     let (rb, ..) = right
                         .char_indices()
                         .find(|(.., c)| c == &'}')
-                        .ok_or_else(|| TrainerError::BracketParse(line, "moves"))?;
+                        .ok_or(TrainerError::BracketParse(line, "moves"))?;
                     let array = &right[lb + 1..rb];
                     pokemon.moves =
                         Some(array.split(',').map(str::trim).map(str::to_owned).collect());
                 }
                 ".heldItem" => pokemon.item = Some(right.to_owned()),
+                ".nature" => pokemon.nature = Some(right.to_owned()),
+                ".ability" => pokemon.ability = Some(right.to_owned()),
+                ".ball" => pokemon.ball = Some(right.to_owned()),
+                ".gender" => pokemon.gender = Some(right.to_owned()),
+                ".isShiny" => pokemon.shiny = right.eq_ignore_ascii_case("TRUE"),
+                ".nickname" => {
+                    // COMPOUND_STRING("Name") or _("Name")
+                    let mut split = right.split('"').skip(1);
+                    let name = split
+                        .next()
+                        .ok_or_else(|| TrainerError::FieldParse(line, right.to_owned()))?;
+                    pokemon.nickname = Some(name.to_owned());
+                }
                 field => return Err(TrainerError::UnknownField(line, field.to_owned())),
             }
         }
@@ -0,0 +1,343 @@
+use hashbrown::HashMap;
+
+use super::{
+    party::{Ivs, Parties, Stats, TrainerPokemon},
+    Trainer, TrainerError,
+};
+
+/// Trainers and parties of a `.party` file
+#[derive(Debug, Default)]
+pub struct PartyFile {
+    pub trainers: HashMap<String, Trainer>,
+    pub parties: Parties,
+    /// Fields that were skipped, as [`TrainerError::UnknownField`]
+    pub skipped: Vec<TrainerError>,
+}
+
+/// Parses the `.party` text format used by pokeemerald-expansion:
+///
+/// ```text
+/// === TRAINER_SAWYER_1 ===
+/// Name: SAWYER
+/// Class: Hiker
+/// Pic: Hiker
+/// Gender: Male
+/// Music: Hiker
+/// Double Battle: No
+/// AI: Check Bad Move / Try To Faint
+///
+/// Rocky (Geodude) (M) @ Oran Berry
+/// Level: 21
+/// IVs: 31 HP / 31 Atk / 31 Def / 31 SpA / 31 SpD / 31 Spe
+/// Ability: Sturdy
+/// - Tackle
+/// - Defense Curl
+/// ```
+///
+/// Names are converted into the constants the C headers would use (`Oran Berry` becomes `ITEM_ORAN_BERRY`),
+/// and every trainer's party is keyed by the trainer's id.
+///
+/// Fields this does not know, such as the ones of party pools, are skipped.
+pub fn parse_party_file(file: &str) -> Result<PartyFile, TrainerError> {
+    let mut trainers = HashMap::new();
+    let mut parties = HashMap::new();
+    let mut skipped = Vec::new();
+
+    let mut current: Option<(String, Trainer)> = None;
+    let mut mons = Vec::new();
+    let mut pokemon: Option<TrainerPokemon> = None;
+    let mut comment = false;
+
+    for (line, text) in file.lines().enumerate() {
+        let text = text.split_once("//").map(|(l, ..)| l).unwrap_or(text).trim();
+
+        if comment {
+            comment = !text.contains("*/");
+            continue;
+        }
+
+        if text.starts_with("/*") {
+            comment = !text.contains("*/");
+            continue;
+        }
+
+        if text.is_empty() {
+            mons.extend(pokemon.take());
+            continue;
+        }
+
+        if let Some(id) = text
+            .strip_prefix("===")
+            .and_then(|text| text.strip_suffix("==="))
+        {
+            mons.extend(pokemon.take());
+            if let Some((id, trainer)) = current.take() {
+                parties.insert(id.clone(), std::mem::take(&mut mons));
+                trainers.insert(id, trainer);
+            }
+            let id = id.trim().to_owned();
+            let trainer = Trainer {
+                party: id.clone(),
+                music: constant("TRAINER_ENCOUNTER_MUSIC_", "Male"),
+                ..Default::default()
+            };
+            current = Some((id, trainer));
+            continue;
+        }
+
+        let (.., trainer) = current
+            .as_mut()
+            .ok_or_else(|| TrainerError::FieldParse(line, text.to_owned()))?;
+
+        match pokemon.as_mut() {
+            None => match trainers_field(line, text, trainer, &mut skipped)? {
+                true => (),
+                false => pokemon = Some(header(text)),
+            },
+            Some(pokemon) => match text.strip_prefix('-') {
+                Some(name) => pokemon
+                    .moves
+                    .get_or_insert_with(Vec::new)
+                    .push(constant("MOVE_", name)),
+                None => pokemon_field(line, text, pokemon, &mut skipped)?,
+            },
+        }
+    }
+
+    mons.extend(pokemon.take());
+    if let Some((id, trainer)) = current.take() {
+        parties.insert(id.clone(), mons);
+        trainers.insert(id, trainer);
+    }
+
+    Ok(PartyFile {
+        trainers,
+        parties,
+        skipped,
+    })
+}
+
+/// Converts a display name such as `Mr. Mime` into a constant such as `SPECIES_MR_MIME`
+pub fn constant(prefix: &str, name: &str) -> String {
+    let mut constant = String::from(prefix);
+    for c in name.trim().chars() {
+        match c {
+            ' ' | '-' => constant.push('_'),
+            '.' | '\'' | '’' | ':' => (),
+            'é' | 'É' => constant.push('E'),
+            '♀' => constant.push_str("_F"),
+            '♂' => constant.push_str("_M"),
+            c => constant.push(c.to_ascii_uppercase()),
+        }
+    }
+    constant
+}
+
+/// Returns false if the line is not a trainer field, which makes it the first line of a pokemon
+fn trainers_field(
+    line: usize,
+    text: &str,
+    trainer: &mut Trainer,
+    skipped: &mut Vec<TrainerError>,
+) -> Result<bool, TrainerError> {
+    let (field, value) = match text.split_once(':') {
+        Some((field, value)) => (field.trim(), value.trim()),
+        None => return Ok(false),
+    };
+    match field {
+        "Name" => trainer.name = (!value.is_empty()).then(|| value.to_owned()),
+        "Class" => trainer.class = constant("TRAINER_CLASS_", value),
+        "Pic" => trainer.pic = constant("TRAINER_PIC_", value),
+        "Gender" => {
+            if value.eq_ignore_ascii_case("Female") {
+                trainer.music = format!("F_TRAINER_FEMALE | {}", trainer.music);
+            }
+        }
+        "Music" => {
+            let music = constant("TRAINER_ENCOUNTER_MUSIC_", value);
+            trainer.music = match trainer.music.starts_with("F_TRAINER_FEMALE") {
+                true => format!("F_TRAINER_FEMALE | {}", music),
+                false => music,
+            };
+        }
+        "Double Battle" => trainer.double_battle = yes(line, value)?,
+        "AI" => {
+            trainer.ai_flags = value
+                .split('/')
+                .map(|flag| constant("AI_FLAG_", flag))
+                .collect()
+        }
+        "Items" => {
+            trainer.items = value
+                .split('/')
+                .map(|item| constant("ITEM_", item))
+                .collect()
+        }
+        // Display only
+        "Mugshot" | "Starting Status" => (),
+        // A pokemon whose nickname or species contains a colon
+        _ if text.contains('(') || text.contains('@') => return Ok(false),
+        field => skipped.push(TrainerError::UnknownField(line, field.to_owned())),
+    }
+    Ok(true)
+}
+
+/// `Nickname (Species) (M) @ Item`, where everything but the species is optional
+fn header(text: &str) -> TrainerPokemon {
+    let (text, item) = match text.split_once('@') {
+        Some((text, item)) => (text.trim(), Some(constant("ITEM_", item))),
+        None => (text, None),
+    };
+
+    let (text, gender) = match text
+        .strip_suffix("(M)")
+        .map(|text| (text, "TRAINER_MON_MALE"))
+        .or_else(|| {
+            text.strip_suffix("(F)")
+                .map(|text| (text, "TRAINER_MON_FEMALE"))
+        }) {
+        Some((text, gender)) => (text.trim(), Some(gender.to_owned())),
+        None => (text, None),
+    };
+
+    let (nickname, species) = match text
+        .strip_suffix(')')
+        .and_then(|text| text.rsplit_once('('))
+    {
+        Some((nickname, species)) => (Some(nickname.trim().to_owned()), species),
+        None => (None, text),
+    };
+
+    TrainerPokemon {
+        species: constant("SPECIES_", species),
+        item,
+        gender,
+        nickname,
+        ..Default::default()
+    }
+}
+
+fn pokemon_field(
+    line: usize,
+    text: &str,
+    pokemon: &mut TrainerPokemon,
+    skipped: &mut Vec<TrainerError>,
+) -> Result<(), TrainerError> {
+    // Showdown writes natures as "Adamant Nature"
+    if let Some(nature) = text.strip_suffix("Nature") {
+        pokemon.nature = Some(constant("NATURE_", nature));
+        return Ok(());
+    }
+    let (field, value) = text
+        .split_once(':')
+        .ok_or_else(|| TrainerError::FieldParse(line, text.to_owned()))?;
+    let (field, value) = (field.trim(), value.trim());
+    match field {
+        "Level" => {
+            pokemon.level = value
+                .parse()
+                .map_err(|err| TrainerError::NumParse(line, "level", err))?
+        }
+        "IVs" => pokemon.ivs = Ivs::Stats(stats(line, "ivs", value, 31)?),
+        "EVs" => pokemon.evs = Some(stats(line, "evs", value, 0)?),
+        "Ability" => pokemon.ability = Some(constant("ABILITY_", value)),
+        "Nature" => pokemon.nature = Some(constant("NATURE_", value)),
+        "Ball" => pokemon.ball = Some(constant("ITEM_", value)),
+        "Shiny" => pokemon.shiny = yes(line, value)?,
+        // Not part of the games this is made for
+        "Happiness" | "Tera Type" | "Dynamax Level" | "Gigantamax" => (),
+        field => skipped.push(TrainerError::UnknownField(line, field.to_owned())),
+    }
+    Ok(())
+}
+
+/// `252 HP / 4 Def / 252 Spe`, where every stat left out is `default`
+fn stats(line: usize, field: &'static str, text: &str, default: u8) -> Result<Stats, TrainerError> {
+    let mut stats = Stats::uniform(default);
+    for stat in text.split('/') {
+        let (value, name) = stat
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| TrainerError::FieldParse(line, text.to_owned()))?;
+        let value = value
+            .parse()
+            .map_err(|err| TrainerError::NumParse(line, field, err))?;
+        match name.trim() {
+            "HP" => stats.hp = value,
+            "Atk" => stats.attack = value,
+            "Def" => stats.defense = value,
+            "SpA" => stats.sp_attack = value,
+            "SpD" => stats.sp_defense = value,
+            "Spe" => stats.speed = value,
+            name => return Err(TrainerError::UnknownField(line, name.to_owned())),
+        }
+    }
+    Ok(stats)
+}
+
+fn yes(line: usize, text: &str) -> Result<bool, TrainerError> {
+    match text {
+        "Yes" => Ok(true),
+        "No" => Ok(false),
+        text => Err(TrainerError::FieldParse(line, text.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_party_file;
+    use crate::trainer::party::{Ivs, Stats};
+
+    #[test]
+    fn party_file() {
+        let file = parse_party_file(
+            "=== TRAINER_SAWYER_1 ===
+Name: SAWYER
+Class: Hiker
+Pic: Hiker
+Gender: Male
+Music: Hiker
+Double Battle: No
+AI: Check Bad Move / Try To Faint
+Party Size: 2
+Pool Rules: Weather Doubles
+
+Rocky (Geodude) (M) @ Oran Berry
+Level: 21
+IVs: 31 HP / 31 Atk / 31 Def / 31 SpA / 31 SpD / 31 Spe
+Ability: Sturdy
+Tags: Lead
+- Tackle
+- Defense Curl
+
+Graveler
+Level: 22
+Adamant Nature
+",
+        )
+        .unwrap();
+
+        let trainer = &file.trainers["TRAINER_SAWYER_1"];
+        assert_eq!(trainer.name.as_deref(), Some("SAWYER"));
+        assert_eq!(trainer.class, "TRAINER_CLASS_HIKER");
+        assert_eq!(trainer.ai_flags, ["AI_FLAG_CHECK_BAD_MOVE", "AI_FLAG_TRY_TO_FAINT"]);
+        assert_eq!(trainer.party, "TRAINER_SAWYER_1");
+
+        let party = &file.parties["TRAINER_SAWYER_1"];
+        assert_eq!(party.len(), 2);
+        assert_eq!(party[0].species, "SPECIES_GEODUDE");
+        assert_eq!(party[0].nickname.as_deref(), Some("Rocky"));
+        assert_eq!(party[0].gender.as_deref(), Some("TRAINER_MON_MALE"));
+        assert_eq!(party[0].item.as_deref(), Some("ITEM_ORAN_BERRY"));
+        assert_eq!(party[0].ivs, Ivs::Stats(Stats::uniform(31)));
+        assert_eq!(
+            party[0].moves.as_deref(),
+            Some(&["MOVE_TACKLE".to_owned(), "MOVE_DEFENSE_CURL".to_owned()][..])
+        );
+        assert_eq!(party[1].species, "SPECIES_GRAVELER");
+        assert_eq!(party[1].nature.as_deref(), Some("NATURE_ADAMANT"));
+
+        // the pool fields and the tags
+        assert_eq!(file.skipped.len(), 3);
+    }
+}
@@ -0,0 +1,95 @@
+use hashbrown::HashMap;
+
+use super::TrainerError;
+
+/// Names of the tables holding rematch trainers (FireRed's VS Seeker data, Emerald's match call data)
+const TABLES: [&str; 2] = ["sVsSeekerData", "gRematchTable"];
+
+/// Parses a rematch table into the trainer that is first fought and the trainers of each rematch, in order.
+///
+/// Every entry of the table is a list of trainer ids, which may be skipped (`SKIP`, `0xFFFF`).
+/// Skipped rematches are kept as `None`, so the index of a rematch stays its VS Seeker stage.
+pub fn parse_rematches(file: &str) -> Result<HashMap<String, Vec<Option<String>>>, TrainerError> {
+    let mut lines = file.lines().enumerate().skip_while(|(.., text)| {
+        !(TABLES.iter().any(|table| text.contains(table)) && text.contains('='))
+    });
+
+    let (line, text) = lines
+        .next()
+        .ok_or(TrainerError::BracketParse(0, "rematch table"))?;
+
+    let (.., text) = text
+        .split_once('{')
+        .ok_or(TrainerError::BracketParse(line, "rematch table"))?;
+
+    let mut rematches = HashMap::new();
+    let mut entry = Vec::new();
+    let mut word = String::new();
+    let mut depth = 0usize;
+
+    for text in std::iter::once(text).chain(lines.map(|(.., text)| text)) {
+        let text = text.split_once("//").map(|(l, ..)| l).unwrap_or(text);
+
+        for c in text.chars().chain(std::iter::once('\n')) {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                word.push(c);
+                continue;
+            }
+
+            let id = std::mem::take(&mut word);
+            if id == "SKIP" || id == "0xFFFF" {
+                entry.push(None);
+            } else if id.starts_with("TRAINER_") && id != "TRAINER_NONE" {
+                entry.push(Some(id));
+            }
+
+            match c {
+                '{' | '(' => depth += 1,
+                '}' | ')' if depth == 0 => {
+                    insert(&mut rematches, &mut entry);
+                    return Ok(rematches);
+                }
+                '}' | ')' => depth -= 1,
+                ',' if depth == 0 => insert(&mut rematches, &mut entry),
+                _ => (),
+            }
+        }
+    }
+
+    Err(TrainerError::BracketParse(line, "rematch table"))
+}
+
+fn insert(rematches: &mut HashMap<String, Vec<Option<String>>>, entry: &mut Vec<Option<String>>) {
+    let mut trainers = std::mem::take(entry).into_iter();
+    if let Some(Some(trainer)) = trainers.next() {
+        rematches.insert(trainer, trainers.collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_rematches;
+
+    #[test]
+    fn skipped_stages() {
+        let rematches = parse_rematches(
+            "static const VsSeekerData sVsSeekerData[] = {
+    { {TRAINER_YOUNGSTER_BEN, TRAINER_YOUNGSTER_BEN_2, SKIP, TRAINER_YOUNGSTER_BEN_3}, MAP_GROUP(ROUTE3), MAP_NUM(ROUTE3) },
+    { {TRAINER_LASS_JANICE, SKIP, TRAINER_LASS_JANICE_2}, MAP_GROUP(ROUTE3), MAP_NUM(ROUTE3) },
+};",
+        )
+        .unwrap();
+        assert_eq!(
+            rematches["TRAINER_YOUNGSTER_BEN"],
+            [
+                Some("TRAINER_YOUNGSTER_BEN_2".to_owned()),
+                None,
+                Some("TRAINER_YOUNGSTER_BEN_3".to_owned()),
+            ]
+        );
+        assert_eq!(
+            rematches["TRAINER_LASS_JANICE"],
+            [None, Some("TRAINER_LASS_JANICE_2".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+
+use dashmap::DashSet;
+use firecore_world_builder::world::pokedex::{
+    item::ItemId, moves::MoveId, pokemon::PokemonId, Dex,
+};
+use hashbrown::HashMap;
+
+use crate::{NameMappings, ParsedData};
+
+/// Resolves `SPECIES_*`, `MOVE_*` and `ITEM_*` constants of the decomp into dex ids.
+///
+/// Species go through the national dex number of `pokedex.h`, moves through their number in `moves.h`
+/// (which is the same as the national move number), and items through their name.
+/// Names that differ from the dex can be aliased to their dex name in the mappings.
+/// Every name that cannot be resolved is remembered for the build report.
+pub struct Constants {
+    species: HashMap<String, PokemonId>,
+    moves: HashMap<String, MoveId>,
+    items: HashMap<String, ItemId>,
+    unresolved: DashSet<String>,
+}
+
+impl Constants {
+    pub fn new(mappings: &NameMappings, data: &ParsedData) -> Self {
+        let headers = &data.constants;
+
+        let species = headers
+            .keys()
+            .flat_map(|name| {
+                let species = name.strip_prefix("SPECIES_")?;
+                let number = *headers.get(&format!("NATIONAL_DEX_{}", species))?;
+                let id = PokemonId::try_from(number).ok()?;
+                data.pokedex.try_get(&id)?;
+                Some((name.clone(), id))
+            })
+            .collect();
+
+        let moves = headers
+            .iter()
+            .flat_map(|(name, number)| {
+                name.strip_prefix("MOVE_")?;
+                let id = MoveId::try_from(*number).ok()?;
+                data.movedex.try_get(&id)?;
+                Some((name.clone(), id))
+            })
+            .collect();
+
+        let items = headers
+            .keys()
+            .flat_map(|name| {
+                let item = name.strip_prefix("ITEM_")?;
+                let id = item
+                    .to_ascii_lowercase()
+                    .parse::<ItemId>()
+                    .ok()
+                    .filter(|id| data.itemdex.try_get(id).is_some())
+                    .or_else(|| {
+                        data.itemdex
+                            .try_get_named(&item.replace('_', " "))
+                            .map(|item| item.id)
+                    })?;
+                Some((name.clone(), id))
+            })
+            .collect();
+
+        let mut constants = Self {
+            species,
+            moves,
+            items,
+            unresolved: Default::default(),
+        };
+
+        for (name, alias) in mappings.constants.aliases.iter() {
+            let resolved = match name.split_once('_').map(|(prefix, ..)| prefix) {
+                Some("SPECIES") => data
+                    .pokedex
+                    .try_get_named(alias)
+                    .map(|pokemon| constants.species.insert(name.clone(), pokemon.id)),
+                Some("MOVE") => data
+                    .movedex
+                    .try_get_named(alias)
+                    .map(|m| constants.moves.insert(name.clone(), m.id)),
+                Some("ITEM") => data
+                    .itemdex
+                    .try_get_named(alias)
+                    .map(|item| constants.items.insert(name.clone(), item.id)),
+                _ => None,
+            };
+            if resolved.is_none() {
+                eprintln!("Cannot resolve alias {} for {}", alias, name);
+            }
+        }
+
+        constants
+    }
+
+    pub fn species(&self, name: &str) -> Option<PokemonId> {
+        self.get(&self.species, name, "SPECIES_NONE")
+    }
+
+    pub fn move_id(&self, name: &str) -> Option<MoveId> {
+        self.get(&self.moves, name, "MOVE_NONE")
+    }
+
+    pub fn item(&self, name: &str) -> Option<ItemId> {
+        self.get(&self.items, name, "ITEM_NONE")
+    }
+
+    /// The `ITEM_*` name of every item, the first in order if several resolve to one
+    pub fn item_names(&self) -> HashMap<ItemId, String> {
+        let mut names = HashMap::<ItemId, String>::with_capacity(self.items.len());
+        for (name, id) in self.items.iter() {
+            let current = names.entry(*id).or_insert_with(|| name.clone());
+            if name < current {
+                *current = name.clone();
+            }
+        }
+        names
+    }
+
+    /// Every constant that was looked up but could not be resolved
+    pub fn unresolved(self) -> BTreeSet<String> {
+        self.unresolved.into_iter().collect()
+    }
+
+    fn get<T: Copy>(&self, table: &HashMap<String, T>, name: &str, none: &str) -> Option<T> {
+        let name = name.trim();
+        if name == none {
+            return None;
+        }
+        let id = table.get(name).copied();
+        if id.is_none() && !self.unresolved.contains(name) {
+            self.unresolved.insert(name.to_owned());
+        }
+        id
+    }
+}
@@ -1,4 +1,4 @@
-use std::{sync::Arc, num::ParseIntError};
+use std::{collections::BTreeSet, sync::Arc};
 
 use dashmap::DashMap;
 use hashbrown::{hash_map::DefaultHashBuilder as RandomState, HashMap};
@@ -10,7 +10,7 @@ use firecore_world_builder::{
             npc::{
                 group::TrainerGroupId,
                 trainer::{NpcTrainer, TrainerDisable},
-                Npc, NpcInteract, NpcMovement, Npcs,
+                Npc, NpcId, NpcInteract, NpcMovement, Npcs,
             },
             trainer::Trainer,
             Character,
@@ -24,12 +24,13 @@ use firecore_world_builder::{
             Brightness, PaletteId, WorldMap, WorldMapSettings, WorldTile,
         },
         pokedex::{
-            item::{Item, ItemStack},
-            moves::{owned::SavedMove, Move},
-            pokemon::{owned::SavedPokemon, stat::StatSet, Pokemon},
+            item::{Item, ItemId, ItemStack},
+            moves::Move,
+            pokemon::Pokemon,
             BasicDex,
         },
-        positions::{BoundingBox, Coordinate, Destination, Direction, Location, Position}, script::{WorldScriptData, WorldInstruction, ScriptId},
+        positions::{BoundingBox, Coordinate, Destination, Direction, Location, Position},
+        script::WorldScriptData,
     },
 };
 use map::{
@@ -39,29 +40,63 @@ use map::{
     JsonConnection, JsonMap,
 };
 use rayon::iter::{
-    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelIterator,
+};
+use script_parser::{
+    inc::{Message, Script},
+    movement::Movement,
+    text::{Charmap, Line, TextLine},
 };
-use script_parser::inc::{Script, Command};
 use serde_json::Value;
 use tinystr::TinyStr16;
 
-use crate::map::JsonMapLayout;
+use crate::{constants::Constants, map::JsonMapLayout, variables::ScriptVariables};
 
 const PATH: &str = "http://raw.githubusercontent.com/pret/pokefirered/master";
 
+/// Directories of the files `data/event_scripts.s` includes for its shared scripts and texts
+const SHARED_SCRIPTS: [&str; 2] = ["data/scripts/", "data/text/"];
+const TRAINER_SCRIPTS: &str = "data/scripts/trainers.inc";
+const EVENT_SCRIPTS: &str = "data/event_scripts.s";
+const PARTY_FILE: &str = "src/data/trainers.party";
+
+mod constants;
 mod edits;
 mod map;
 mod mapping;
+mod report;
+mod script;
+mod text;
+mod trainer;
+mod variables;
 
 pub use mapping::*;
 pub use edits::*;
+pub use report::{BuildReport, CommandCoverage, FlowReport, LoweringReport};
+pub use script::{
+    decompile, decompile_world, Comparison, Instruction, Interpreter, InterpreterError, Menu,
+    MovementStep, ScriptData, ScriptEvent, ScriptState, WarpKind,
+};
+pub use trainer::{AiFlag, Rematch, TrainerInfo};
+pub use variables::{ScriptVariables, UnknownVariables};
 // mod serializable;
 
 type Maps = DashMap<String, JsonMap, RandomState>;
 type Scripts = DashMap<String, Script, RandomState>;
-type Messages = DashMap<String, Vec<Vec<String>>, RandomState>;
+type Movements = DashMap<String, Movement, RandomState>;
+type Messages = DashMap<String, Vec<TextLine<Line>>, RandomState>;
+type Sources = DashMap<String, BTreeSet<String>, RandomState>;
+/// Problems found while converting, by the label they are found in
+type Problems = DashMap<String, BTreeSet<String>, RandomState>;
 type Trainers = HashMap<String, script_parser::trainer::Trainer>;
 type Parties = HashMap<String, Vec<script_parser::trainer::party::TrainerPokemon>>;
+type Names = HashMap<String, String>;
+type Rematches = HashMap<String, Vec<Option<String>>>;
+/// Intro and defeat text labels by trainer
+type RematchTexts = HashMap<String, (String, String)>;
+type Defines = HashMap<String, i64>;
+type Menus = HashMap<String, Vec<String>>;
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct ParsedData {
@@ -71,22 +106,75 @@ pub struct ParsedData {
     pub movedex: BasicDex<Move, Arc<Move>>,
     pub itemdex: BasicDex<Item, Arc<Item>>,
     pub scripts: Scripts,
+    pub movements: Movements,
     pub messages: Messages,
+    /// Every file defining a label of [`Self::scripts`], [`Self::movements`] or [`Self::messages`],
+    /// where the definition of the first one is kept
+    pub sources: Sources,
+    /// Labels a file defines more than once, with those files, where the first definition is kept
+    pub redefined: Sources,
     pub trainers: Trainers,
     pub parties: Parties,
+    pub species_names: Names,
+    pub class_names: Names,
+    pub rematches: Rematches,
+    /// Texts of each trainer's `trainerbattle_rematch`, see [`Self::rematches`]
+    pub rematch_texts: RematchTexts,
+    /// Constants of the decomp's species, move, item and pokedex headers
+    pub constants: Defines,
+    /// Options of every multichoice menu by `MULTICHOICE_*` id
+    pub menus: Menus,
+    /// Constants of `flags.h`
+    pub flags: Defines,
+    /// Constants of `vars.h`
+    pub vars: Defines,
+    /// Constants that script arguments are written with, such as `NO` of `global.h`
+    /// and `MULTI_B_PRESSED` of `script_menu.h`
+    pub script_constants: Defines,
+    /// The encoding of the games' text, which trainer and species names are hashed in
+    pub charmap: Charmap,
 }
 
 pub struct WorldData {
     pub maps: HashMap<Location, WorldMap>,
-    pub scripts: WorldScriptData,
+    pub scripts: ScriptData,
+    /// The scripts lowered for the world's script engine, see [`ScriptData::lower`]
+    pub world_scripts: WorldScriptData,
+    pub variables: ScriptVariables,
+    pub trainers: HashMap<Location, HashMap<NpcId, TrainerInfo>>,
+    /// The `ITEM_*` names of items, which [`decompile`] writes them as
+    pub items: HashMap<ItemId, String>,
+    pub report: BuildReport,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CompileOptions {
+    /// Replace flag and var names in scripts with their ids.
+    ///
+    /// Trainers need no ids, as [`TrainerDisable`] names the NPCs they disable
+    /// and the world keeps their defeats itself, without a flag.
+    pub numeric_ids: bool,
+    /// Keep scripts with commands that cannot be converted, see [`Instruction::Unsupported`]
+    pub keep_unsupported: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParseOptions {
+    /// Prefix the labels of a map's `scripts.inc` and `text.inc` with the map, `PalletTown__PalletTown_EventScript_Sign`,
+    /// so maps defining the same label keep their own definitions.
+    /// References from other maps and the shared scripts are pointed at the new labels too.
+    pub namespace_scripts: bool,
 }
 
 pub fn compile(
-    mappings: NameMappings,
+    mappings: &NameMappings,
     edits: edits::Edits,
     mut data: ParsedData,
+    options: CompileOptions,
 ) -> anyhow::Result<WorldData> {
 
+    let constants = Constants::new(mappings, &data);
+
     println!("Converting wild encounters...");
 
     eprintln!("TODO: fix fishing encounters");
@@ -101,16 +189,16 @@ pub fn compile(
         .for_each(|e| {
             let mut entries = HashMap::new();
             if let Some(e) = e.land_mons {
-                entries.insert(WildType::Land, e.into(&data.pokedex));
+                entries.insert(WildType::Land, e.into(&constants));
             }
             if let Some(e) = e.water_mons {
-                entries.insert(WildType::Water, e.into(&data.pokedex));
+                entries.insert(WildType::Water, e.into(&constants));
             }
             if let Some(e) = e.rock_smash_mons {
-                entries.insert(WildType::Rock, e.into(&data.pokedex));
+                entries.insert(WildType::Rock, e.into(&constants));
             }
             if let Some(e) = e.fishing_mons {
-                entries.insert(WildType::Fishing(0), e.into(&data.pokedex));
+                entries.insert(WildType::Fishing(0), e.into(&constants));
             }
             if entries.is_empty() {
                 encounters.insert(e.map, None);
@@ -122,13 +210,25 @@ pub fn compile(
     println!("Created {} wild encounters", encounters.len());
 
     let new_maps = DashMap::<Location, WorldMap>::new();
+    let trainers = DashMap::new();
+    let trainer_problems = Problems::default();
 
     println!("Converting maps...");
 
     data.maps.par_iter().for_each(|map| {
         let map = map.value();
         println!("Converting {}", map.data.name);
-        if let Some(map) = into_world_map(&mappings, &data, &encounters, map) {
+        if let Some((map, info)) = into_world_map(
+            mappings,
+            &data,
+            &constants,
+            &encounters,
+            &trainer_problems,
+            map,
+        ) {
+            if !info.is_empty() {
+                trainers.insert(map.id, info);
+            }
             if let Some(removed) = new_maps.insert(map.id, map) {
                 panic!("Duplicate world map id {}", removed.id);
             }
@@ -153,9 +253,61 @@ pub fn compile(
 
     println!("Done!");
 
+    let mut report = BuildReport::default();
+
+    let mut scripts = script::create_script_data(
+        mappings,
+        &constants,
+        &data,
+        options.keep_unsupported,
+        &mut report,
+    );
+
+    let variables = ScriptVariables::new(&data);
+
+    report.unknown = variables.check(&mut scripts, options.numeric_ids);
+
+    report.flow = script::analyze(&scripts, &data);
+
+    report.duplicates = data
+        .sources
+        .iter()
+        .filter(|sources| sources.value().len() > 1)
+        .map(|sources| (sources.key().clone(), sources.value().clone()))
+        .collect();
+
+    for redefined in data.redefined.iter() {
+        report
+            .duplicates
+            .entry(redefined.key().clone())
+            .or_default()
+            .extend(redefined.value().iter().cloned());
+    }
+
+    let items = constants.item_names();
+
+    report.unresolved = constants.unresolved();
+
+    report.trainers = trainer_problems.into_iter().collect();
+
+    let (world_scripts, lowering) = scripts.lower(&variables);
+
+    println!(
+        "Lowered {} scripts, {} of them without a conditional jump that cannot be tested",
+        scripts.scripts.len(),
+        lowering.branches.len()
+    );
+
+    report.lowering = lowering;
+
     Ok(WorldData {
         maps: new_maps.into_par_iter().collect(),
-        scripts: create_world_script_data(&mappings, &data.scripts, &data.messages),
+        scripts,
+        world_scripts,
+        variables,
+        trainers: trainers.into_par_iter().collect(),
+        items,
+        report,
     })
 
 }
@@ -173,7 +325,7 @@ impl core::fmt::Display for OptionError {
     }
 }
 
-pub fn create_data() -> anyhow::Result<ParsedData> {
+pub fn create_data(options: ParseOptions) -> anyhow::Result<ParsedData> {
     eprintln!("Parsed map file cannot be read!");
     eprintln!("Generating new parsed map file...");
 
@@ -214,14 +366,126 @@ pub fn create_data() -> anyhow::Result<ParsedData> {
     let trainers = attohttpc::get(format!("{}/src/data/trainers.h", PATH))
         .send()?
         .text_utf8()?;
-    let trainers = script_parser::trainer::parse_trainers(&trainers)?;
+    let mut trainers = script_parser::trainer::parse_trainers(&trainers)?;
 
     println!("Getting trainer parties...");
 
     let parties = attohttpc::get(format!("{}/src/data/trainer_parties.h", PATH))
         .send()?
         .text_utf8()?;
-    let parties = script_parser::trainer::party::parse_parties(&parties)?;
+    let mut parties = script_parser::trainer::party::parse_parties(&parties)?;
+
+    println!("Getting trainer party file...");
+
+    // pokeemerald-expansion keeps its trainers in the `.party` format instead
+    if let Some(file) = attohttpc::get(format!("{}/{}", PATH, PARTY_FILE))
+        .send()
+        .ok()
+        .filter(|r| r.is_success())
+        .and_then(|r| r.text_utf8().ok())
+    {
+        let file = script_parser::trainer::party_file::parse_party_file(&file)?;
+        for skipped in file.skipped {
+            eprintln!("trainers.party: {}", skipped);
+        }
+        println!("Parsed {} trainers of the party file", file.trainers.len());
+        trainers.extend(file.trainers);
+        parties.extend(file.parties);
+    }
+
+    println!("Getting species names...");
+
+    let species_names = attohttpc::get(format!("{}/src/data/text/species_names.h", PATH))
+        .send()?
+        .text_utf8()?;
+    let species_names = script_parser::names::parse_names(&species_names);
+
+    println!("Getting trainer class names...");
+
+    let class_names = attohttpc::get(format!("{}/src/data/text/trainer_class_names.h", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    let class_names = script_parser::names::parse_names(&class_names);
+
+    println!("Getting rematch tables...");
+
+    let rematches = attohttpc::get(format!("{}/src/vs_seeker.c", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    let rematches = script_parser::trainer::rematch::parse_rematches(&rematches)?;
+
+    println!("Getting constants...");
+
+    let mut constants = Defines::new();
+
+    for header in ["species", "pokedex", "moves", "items"] {
+        let header = attohttpc::get(format!("{}/include/constants/{}.h", PATH, header))
+            .send()?
+            .error_for_status()?
+            .text_utf8()?;
+        script_parser::constants::parse_constants(&header, &mut constants);
+    }
+
+    println!("Getting flags and vars...");
+
+    let mut flags = Defines::new();
+
+    let header = attohttpc::get(format!("{}/include/constants/flags.h", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    script_parser::constants::parse_constants(&header, &mut flags);
+
+    let mut vars = Defines::new();
+
+    let header = attohttpc::get(format!("{}/include/constants/vars.h", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    script_parser::constants::parse_constants(&header, &mut vars);
+
+    let mut script_constants = Defines::new();
+
+    for header in ["global", "script_menu"] {
+        let header = attohttpc::get(format!("{}/include/constants/{}.h", PATH, header))
+            .send()?
+            .error_for_status()?
+            .text_utf8()?;
+        script_parser::constants::parse_constants(&header, &mut script_constants);
+    }
+
+    println!("Getting charmap...");
+
+    let charmap = attohttpc::get(format!("{}/charmap.txt", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    let charmap = Charmap::parse(&charmap);
+
+    println!("Getting multichoice menus...");
+
+    let strings = attohttpc::get(format!("{}/src/strings.c", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    let strings = script_parser::names::parse_strings(&strings);
+
+    let menus = attohttpc::get(format!("{}/src/data/script_menu.h", PATH))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
+    let menus = script_parser::menu::parse_multichoices(&menus)
+        .into_iter()
+        .map(|(id, options)| {
+            let options = options
+                .into_iter()
+                .map(|option| strings.get(&option).cloned().unwrap_or(option))
+                .collect();
+            (id, options)
+        })
+        .collect::<Menus>();
 
     println!("Getting layouts...");
 
@@ -266,8 +530,11 @@ pub fn create_data() -> anyhow::Result<ParsedData> {
     println!("Found {} map names", names.len());
 
     let maps: Maps = Default::default();
-    let mut scripts: Scripts = Default::default();
+    let scripts: Scripts = Default::default();
+    let movements: Movements = Default::default();
     let messages: Messages = Default::default();
+    let sources: Sources = Default::default();
+    let redefined: Sources = Default::default();
 
     let layouts = layouts
         .layouts
@@ -276,75 +543,192 @@ pub fn create_data() -> anyhow::Result<ParsedData> {
         .map(|l| (l.id.clone(), l))
         .collect::<DashMap<String, JsonMapLayout, RandomState>>();
 
-    names.into_par_iter().for_each(|map| {
-        let path = format!("{}/data/maps/{}/map.json", PATH, map);
-        let scripts_path = format!("{}/data/maps/{}/scripts.inc", PATH, map);
-        let text_path = format!("{}/data/maps/{}/text.inc", PATH, map);
-
-        let data = attohttpc::get(path)
-            .send()
-            .unwrap_or_else(|err| panic!("Could not get {} with error {}", map, err))
-            .json::<map::JsonMapData>()
-            .unwrap_or_else(|err| panic!("Could not get {} with error {}", map, err));
-
-        if let Some(scripts_data) = attohttpc::get(scripts_path)
-            .send()
-            .ok()
-            .map(|r| r.text().ok())
-            .flatten()
-        {
-            match script_parser::inc::parse(&scripts_data) {
-                Ok(scripts_data) => {
-                    for script in scripts_data {
-                        scripts.insert(script.name.clone(), script);
+    let mut parsed_maps = names
+        .into_par_iter()
+        .map(|map| {
+            let path = format!("{}/data/maps/{}/map.json", PATH, map);
+            let scripts_path = format!("{}/data/maps/{}/scripts.inc", PATH, map);
+            let text_path = format!("{}/data/maps/{}/text.inc", PATH, map);
+
+            let data = attohttpc::get(path)
+                .send()
+                .unwrap_or_else(|err| panic!("Could not get {} with error {}", map, err))
+                .json::<map::JsonMapData>()
+                .unwrap_or_else(|err| panic!("Could not get {} with error {}", map, err));
+
+            let scripts_data = attohttpc::get(scripts_path)
+                .send()
+                .ok()
+                .map(|r| r.text().ok())
+                .flatten()
+                .map(|scripts_data| {
+                    let (scripts_data, diagnostics) = script_parser::inc::parse(&scripts_data);
+                    for diagnostic in diagnostics {
+                        eprintln!("{}/scripts.inc:{}", map, diagnostic);
                     }
-                }
-                Err(err) => {
-                    println!("Could not parse script for map {} with error {}", map, err)
-                }
-            }
-        }
+                    scripts_data
+                })
+                .unwrap_or_default();
+
+            let message_data = attohttpc::get(text_path)
+                .send()
+                .ok()
+                .map(|r| r.text().ok())
+                .flatten()
+                .map(|message_data| {
+                    let (message_data, diagnostics) = script_parser::inc::parse(&message_data);
+                    for diagnostic in diagnostics {
+                        eprintln!("{}/text.inc:{}", map, diagnostic);
+                    }
+                    message_data
+                })
+                .unwrap_or_default();
 
-        if let Some(message_data) = attohttpc::get(text_path)
-            .send()
-            .ok()
-            .map(|r| r.text().ok())
-            .flatten()
-        {
-            if let Ok(message_data) = script_parser::inc::parse_message_script(&message_data) {
-                for message in message_data {
-                    messages.insert(message.name, message.text);
-                }
-            }
-        }
+            (map, data, scripts_data, message_data)
+        })
+        .collect::<Vec<_>>();
 
-        let layout = layouts
-            .get(&data.layout)
-            .unwrap_or_else(|| panic!("Could not get map layout {}", data.layout));
+    println!("Getting trainer scripts...");
 
-        let layout = layout.value().clone();
+    let trainer_scripts = attohttpc::get(format!("{}/{}", PATH, TRAINER_SCRIPTS))
+        .send()?
+        .error_for_status()?
+        .text_utf8()?;
 
-        println!("Parsed map {}", data.name);
+    println!("Parsing trainer scripts...");
 
-        if let Some(removed) = maps.insert(data.id.clone(), JsonMap { data, layout }) {
-            panic!("Map {} was removed!", removed.data.name);
-        }
-    });
+    let (mut trainer_scripts, diagnostics) = script_parser::inc::parse(&trainer_scripts);
 
-    println!("Getting trainer scripts...");
+    for diagnostic in diagnostics {
+        eprintln!("trainers.inc:{}", diagnostic);
+    }
+
+    println!("Getting shared event scripts...");
 
-    let trainer_scripts = attohttpc::get(format!("{}/data/scripts/trainers.inc", PATH))
+    let event_scripts = attohttpc::get(format!("{}/{}", PATH, EVENT_SCRIPTS))
         .send()?
+        .error_for_status()?
         .text_utf8()?;
 
-    println!("Parsing trainer scripts...");
+    let (mut shared_scripts, diagnostics) =
+        script_parser::inc::parse_with(&event_scripts, |file| {
+            // map scripts are read with their maps,
+            // and the macros of asm/ would expand every command into bytes
+            if file == TRAINER_SCRIPTS || !SHARED_SCRIPTS.iter().any(|dir| file.starts_with(dir)) {
+                return Ok(None);
+            }
+            println!("Getting {}", file);
+            attohttpc::get(format!("{}/{}", PATH, file))
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.text_utf8())
+                .map(Some)
+                .map_err(|err| err.to_string())
+        });
 
-    scripts.extend(
-        script_parser::inc::parse(&trainer_scripts)?
-            .into_iter()
-            .map(|s| (s.name.clone(), s)),
+    for diagnostic in diagnostics {
+        eprintln!("event_scripts.s:{}", diagnostic);
+    }
+
+    println!("Parsed {} shared scripts", shared_scripts.len());
+
+    if options.namespace_scripts {
+        println!("Namespacing map scripts...");
+
+        // every map's labels are known before any file is rewritten
+        let namespaces = script::Namespaces::new(
+            parsed_maps
+                .iter()
+                .flat_map(|(map, _, scripts_data, message_data)| {
+                    scripts_data
+                        .iter()
+                        .chain(message_data.iter())
+                        .map(move |script| (*map, script))
+                }),
+            trainer_scripts.iter().chain(shared_scripts.iter()),
+        );
+
+        parsed_maps
+            .par_iter_mut()
+            .for_each(|(map, data, scripts_data, message_data)| {
+                namespaces.rewrite(
+                    Some(*map),
+                    &format!("data/maps/{}/scripts.inc", map),
+                    scripts_data,
+                );
+                namespaces.rewrite(
+                    Some(*map),
+                    &format!("data/maps/{}/text.inc", map),
+                    message_data,
+                );
+                namespaces.rewrite_events(map, data);
+            });
+        namespaces.rewrite(None, TRAINER_SCRIPTS, &mut trainer_scripts);
+        namespaces.rewrite(None, EVENT_SCRIPTS, &mut shared_scripts);
+    }
+
+    parsed_maps
+        .into_par_iter()
+        .for_each(|(map, data, scripts_data, message_data)| {
+            insert_scripts(
+                &format!("data/maps/{}/scripts.inc", map),
+                scripts_data,
+                &charmap,
+                &scripts,
+                &movements,
+                &messages,
+                &sources,
+                &redefined,
+            );
+
+            insert_scripts(
+                &format!("data/maps/{}/text.inc", map),
+                message_data,
+                &charmap,
+                &scripts,
+                &movements,
+                &messages,
+                &sources,
+                &redefined,
+            );
+
+            let layout = layouts
+                .get(&data.layout)
+                .unwrap_or_else(|| panic!("Could not get map layout {}", data.layout));
+
+            let layout = layout.value().clone();
+
+            println!("Parsed map {}", data.name);
+
+            if let Some(removed) = maps.insert(data.id.clone(), JsonMap { data, layout }) {
+                panic!("Map {} was removed!", removed.data.name);
+            }
+        });
+
+    insert_scripts(
+        TRAINER_SCRIPTS,
+        trainer_scripts,
+        &charmap,
+        &scripts,
+        &movements,
+        &messages,
+        &sources,
+        &redefined,
     );
 
+    insert_scripts(
+        EVENT_SCRIPTS,
+        shared_scripts,
+        &charmap,
+        &scripts,
+        &movements,
+        &messages,
+        &sources,
+        &redefined,
+    );
+
+    let rematch_texts = trainer::rematch_texts(&scripts);
+
     let data = ParsedData {
         maps,
         wild,
@@ -352,9 +736,22 @@ pub fn create_data() -> anyhow::Result<ParsedData> {
         movedex,
         itemdex,
         scripts,
+        movements,
         messages,
+        sources,
+        redefined,
         trainers,
         parties,
+        species_names,
+        class_names,
+        rematches,
+        rematch_texts,
+        constants,
+        menus,
+        script_constants,
+        flags,
+        vars,
+        charmap,
     };
 
     println!("Done parsing maps!");
@@ -370,12 +767,97 @@ pub fn create_data() -> anyhow::Result<ParsedData> {
     Ok(data)
 }
 
+/// Sorts parsed scripts into movement blocks, text labels decoded through the charmap and event scripts.
+///
+/// Files are parsed in any order, so a label defined by several files keeps the definition of the file sorting first.
+/// A label a file defines again keeps its first definition in the file.
+#[allow(clippy::too_many_arguments)]
+fn insert_scripts(
+    source: &str,
+    parsed: Vec<Script>,
+    charmap: &Charmap,
+    scripts: &Scripts,
+    movements: &Movements,
+    messages: &Messages,
+    sources: &Sources,
+    redefined: &Sources,
+) {
+    for script in parsed {
+        // held until the script is inserted, so files defining the same label take turns
+        let mut defined = sources.entry(script.name.clone()).or_default();
+        if !defined.insert(source.to_owned()) {
+            eprintln!("{}: label {} is defined twice", source, script.name);
+            redefined
+                .entry(script.name.clone())
+                .or_default()
+                .insert(source.to_owned());
+            continue;
+        }
+        if defined.len() > 1 {
+            eprintln!(
+                "{}: label {} is also defined in {}",
+                source,
+                script.name,
+                defined
+                    .iter()
+                    .filter(|file| file.as_str() != source)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if defined.iter().next().map(String::as_str) != Some(source) {
+                continue;
+            }
+            // the label may have been of another kind in the file defining it before
+            scripts.remove(&script.name);
+            movements.remove(&script.name);
+            messages.remove(&script.name);
+        }
+
+        if let Some(movement) = Movement::from_script(&script) {
+            for step in movement.unknown() {
+                eprintln!(
+                    "{}: unknown movement step {} in {}",
+                    source, step, movement.name
+                );
+            }
+            movements.insert(movement.name.clone(), movement);
+        } else if let Some(message) = Message::from_script(&script) {
+            let mut missing = BTreeSet::new();
+            let text = message
+                .text
+                .iter()
+                .map(|line| {
+                    line.map(|text| {
+                        let (line, characters) = charmap.decode(text);
+                        missing.extend(characters);
+                        line
+                    })
+                })
+                .collect();
+            if !missing.is_empty() {
+                eprintln!(
+                    "{}: {} has characters the charmap does not have: {}",
+                    source,
+                    message.name,
+                    missing.into_iter().collect::<String>()
+                );
+            }
+            messages.insert(message.name, text);
+        } else {
+            scripts.insert(script.name.clone(), script);
+        }
+    }
+}
+
 fn into_world_map(
     mappings: &NameMappings,
     data: &ParsedData,
+    constants: &Constants,
     encounters: &DashMap<String, Option<HashMap<WildType, WildEntry>>>,
+    problems: &Problems,
     map: &JsonMap,
-) -> Option<WorldMap> {
+) -> Option<(WorldMap, HashMap<NpcId, TrainerInfo>)> {
     let map_path = format!("{}/{}", PATH, map.layout.blockdata_filepath);
     let border_path = format!("{}/{}", PATH, map.layout.border_filepath);
 
@@ -394,12 +876,7 @@ fn into_world_map(
         &map.layout.secondary_tileset,
     );
 
-    let id = mappings
-        .map
-        .id
-        .get(&map.data.id)
-        .cloned()
-        .unwrap_or_else(|| loc(&map.data.id));
+    let id = map_location(mappings, &map.data.id);
 
     let border = mapdata
         .border
@@ -414,7 +891,9 @@ fn into_world_map(
         })
         .collect::<Vec<_>>();
 
-    Some(WorldMap {
+    let trainers = DashMap::new();
+
+    let map = WorldMap {
         id,
         name: mappings
             .map
@@ -453,9 +932,16 @@ fn into_world_map(
             .flat_map(|warp| into_world_warp(mappings, &data.maps, warp))
             .collect(),
         wild: encounters.remove(&map.data.id).map(|(.., v)| v).flatten(),
-        npcs: into_world_npcs(mappings, data, &map.data.object_events),
+        npcs: into_world_npcs(
+            mappings,
+            data,
+            constants,
+            &trainers,
+            problems,
+            &map.data.object_events,
+        ),
         objects: into_world_objects(mappings, &map.data.object_events),
-        items: into_world_items(data, &map.data.bg_events),
+        items: into_world_items(constants, &map.data.bg_events),
         signs: into_world_signs(data, &map.data.bg_events),
         settings: WorldMapSettings {
             fly_position: None,
@@ -471,140 +957,9 @@ fn into_world_map(
                 .unwrap_or_else(|| WorldMapSettings::default_transition()),
         },
         // scripts: Default::default(),
-    })
-}
-
-fn create_world_script_data(mappings: &NameMappings, scripts: &Scripts, messages: &Messages) -> WorldScriptData {
-    WorldScriptData {
-        scripts: scripts
-            .par_iter()
-            .flat_map(|r| {
-                let k = r.key();
-                let k = k.clone();
-                let v = r.value();
-                let args: Vec<_> = v
-                    .commands
-                    .iter()
-                    .map(|c| match into_instruction(mappings, &k, c) {
-                        Ok(i) => Some(i),
-                        Err(err) => match err {
-                            InstructionError::Unknown(..) => None,
-                            InstructionError::ParseInt(..) => None,
-                            err => panic!("{}", err),
-                        },
-                    })
-                    .collect();
-                if args.contains(&None) {
-                    return None;
-                }
-                (!args.is_empty()).then(|| (k, args.into_iter().flatten().collect()))
-            })
-            .collect(),
-        messages: messages
-            .par_iter()
-            .map(|r| (r.key().clone(), r.value().clone()))
-            .collect(),
-    }
-}
-
-fn into_instruction(
-    mappings: &crate::NameMappings,
-    id: &ScriptId,
-    command: &Command,
-) -> Result<WorldInstruction, InstructionError> {
-    Ok(match command.command.as_str() {
-        "end" => WorldInstruction::End,
-        "return" => WorldInstruction::Return,
-        // set variables
-        "setvar" => WorldInstruction::SetVar(
-            command.arguments[0].clone(),
-            command.arguments[1].parse().map_err(|err| {
-                InstructionError::ParseInt(id.clone(), command.arguments[1].clone(), err)
-            })?,
-        ),
-        "setflag" => WorldInstruction::SetFlag(command.arguments[0].clone()),
-        "specialvar" => {
-            WorldInstruction::SpecialVar(command.arguments[0].clone(), command.arguments[1].clone())
-        }
-        // compare
-        "compare" => WorldInstruction::Compare(
-            command.arguments[0].clone(),
-            match command.arguments[1].as_str() {
-                "TRUE" => 1,
-                "FALSE" => 0,
-                other => other.parse().map_err(|err| {
-                    InstructionError::ParseInt(id.clone(), command.arguments[1].clone(), err)
-                })?,
-            },
-        ),
-        "call" => WorldInstruction::Call(command.arguments[0].clone()),
-        // goto/call
-        "goto_if_eq" => WorldInstruction::GotoIfEq(command.arguments[0].clone()),
-        "goto_if_set" => {
-            WorldInstruction::GotoIfSet(command.arguments[0].clone(), command.arguments[1].clone())
-        }
-        // Player Freezing
-        "lock" => WorldInstruction::Lock,
-        "release" => WorldInstruction::Release,
-        // NPC commands
-        "faceplayer" => WorldInstruction::FacePlayer,
-        "walk_down" => WorldInstruction::Walk(Direction::Down),
-        "walk_up" => WorldInstruction::Walk(Direction::Up),
-        "walk_left" => WorldInstruction::Walk(Direction::Left),
-        "walk_right" => WorldInstruction::Walk(Direction::Right),
-        // Singular trainer battle
-        "trainerbattle_single" => WorldInstruction::TrainerBattleSingle,
-        // Message
-        "msgbox" => WorldInstruction::Msgbox(
-            command.arguments[0].clone(),
-            command.arguments.get(1).cloned(),
-        ),
-        "textcolor" => WorldInstruction::TextColor(command.arguments[0].parse().unwrap()),
-        "message" => WorldInstruction::Message(command.arguments[0].clone()),
-        "waitmessage" => WorldInstruction::WaitMessage,
-        // Sound
-        "playfanfare" => {
-            let (id, var) = mappings
-                .audio
-                .sounds
-                .get(&command.arguments[0][4..])
-                .ok_or(InstructionError::MissingMapping(
-                    id.clone(),
-                    command.arguments[0][4..].to_owned(),
-                ))?;
-            WorldInstruction::PlayFanfare(*id, *var)
-        }
-        "waitfanfare" => WorldInstruction::WaitMessage,
-        // Item
-        "additem" => WorldInstruction::AddItem(command.arguments[0][5..].parse().unwrap()),
-        "checkitemspace" => WorldInstruction::CheckItemSpace(
-            command.arguments[0].clone(),
-            command.arguments[1].parse().unwrap(),
-        ),
-        "getitemname" => WorldInstruction::GetItemName(
-            command.arguments[0].parse().map_err(|err| {
-                InstructionError::ParseInt(id.clone(), command.arguments[0].clone(), err)
-            })?,
-            command.arguments[1].clone(),
-        ),
-        com => return Err(InstructionError::Unknown(id.to_string(), com.to_owned())),
-    })
-}
+    };
 
-#[derive(Debug)]
-enum InstructionError {
-    Unknown(ScriptId, String),
-    ParseInt(ScriptId, String, ParseIntError),
-    ParseStr(ScriptId, String, tinystr::TinyStrError),
-    MissingMapping(ScriptId, String),
-}
-
-impl std::error::Error for InstructionError {}
-
-impl std::fmt::Display for InstructionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(&self, f)
-    }
+    Some((map, trainers.into_iter().collect()))
 }
 
 fn loc(id: &str) -> Location {
@@ -614,8 +969,18 @@ fn loc(id: &str) -> Location {
     }
 }
 
+/// The location of a `MAP_*` id, through the map id mappings if it has one
+fn map_location(mappings: &NameMappings, map: &str) -> Location {
+    mappings
+        .map
+        .id
+        .get(map)
+        .cloned()
+        .unwrap_or_else(|| loc(map))
+}
+
 fn truncate_id(id: &str) -> TinyStr16 {
-    let id = &id[4..];
+    let id = id.strip_prefix("MAP_").unwrap_or(id);
     if id.len() >= 16 {
         format!("{}{}", &id[..12], &id[id.len() - 4..]).parse()
     } else {
@@ -641,12 +1006,7 @@ fn into_chunk(mappings: &NameMappings, json_connections: &[JsonConnection]) -> O
                     connections.insert(direction, Vec::new());
                 }
                 connections.get_mut(&direction).unwrap().push(Connection(
-                    mappings
-                        .map
-                        .id
-                        .get(&connection.map)
-                        .cloned()
-                        .unwrap_or_else(|| loc(&connection.map)),
+                    map_location(mappings, &connection.map),
                     connection.offset as _,
                 ))
             }
@@ -660,12 +1020,7 @@ fn into_world_warp(
     maps: &Maps,
     warp: &JsonWarpEvent,
 ) -> Option<WarpEntry> {
-    let destination = mappings
-        .map
-        .id
-        .get(&warp.destination)
-        .cloned()
-        .unwrap_or_else(|| loc(&warp.destination));
+    let destination = map_location(mappings, &warp.destination);
 
     // let name = format!("warp_{}", index).parse().unwrap();
 
@@ -707,7 +1062,14 @@ fn into_world_warp(
     Some(entry)
 }
 
-fn into_world_npcs(mappings: &NameMappings, data: &ParsedData, events: &[JsonObjectEvent]) -> Npcs {
+fn into_world_npcs(
+    mappings: &NameMappings,
+    data: &ParsedData,
+    constants: &Constants,
+    trainers: &DashMap<NpcId, TrainerInfo>,
+    problems: &Problems,
+    events: &[JsonObjectEvent],
+) -> Npcs {
     events
         .par_iter()
         .enumerate()
@@ -723,6 +1085,7 @@ fn into_world_npcs(mappings: &NameMappings, data: &ParsedData, events: &[JsonObj
                 let mut interact = NpcInteract::Nothing;
 
                 let mut trainer = None;
+                let mut info = None;
                 let mut name = String::new();
 
                 if let Some(script) = data.scripts.get(&event.script) {
@@ -737,126 +1100,46 @@ fn into_world_npcs(mappings: &NameMappings, data: &ParsedData, events: &[JsonObj
                     // }
 
                     if !(event.trainer_type.eq_ignore_ascii_case("TRAINER_TYPE_NONE")) {
-                        if let Some(battle) = script.commands.iter().find(|command| {
+                        if let Some(position) = script.commands.iter().position(|command| {
                             command.command.eq_ignore_ascii_case("trainerbattle_single")
                         }) {
-                            let mut args = battle.arguments.iter();
-                            let id = args.next().unwrap();
-                            let encounter_id = args.next().unwrap();
-                            let defeat_id = args.next().unwrap();
-                            let t = data.trainers.get(id).unwrap();
-                            let party = data
-                                .parties
-                                .get(&t.party)
-                                .unwrap_or_else(|| panic!("Could not get party for {}", id));
-                            let sight = event.trainer_sight_or_berry_tree_id.parse().unwrap();
-                            if let Some(trainer_name) = &t.name {
-                                name = trainer_name.clone();
-                            }
-
-                            fn get_group(t: &script_parser::trainer::Trainer) -> TrainerGroupId {
-                                fn get(
-                                    t: &script_parser::trainer::Trainer,
-                                ) -> Option<TrainerGroupId> {
-                                    let text = t.pic.split_once("TRAINER_PIC_").map(|(.., r)| r)?;
-                                    let split = text.split_once('_')?;
-                                    if split.0.eq_ignore_ascii_case("RS") {
-                                        return None;
+                            match npc_trainer(
+                                mappings, data, constants, events, index, event, script, position,
+                                problems,
+                            ) {
+                                Ok((trainer_name, npc, trainer_info)) => {
+                                    if let Some(trainer_name) = trainer_name {
+                                        name = trainer_name;
+                                    }
+                                    trainer = Some(npc);
+                                    info = Some(trainer_info);
+
+                                    // talking to a defeated trainer
+                                    match trainer::post_battle_message(
+                                        data,
+                                        &script.commands[position + 1..],
+                                    )
+                                    .and_then(|id| data.messages.get(&id))
+                                    {
+                                        Some(message) => {
+                                            interact =
+                                                NpcInteract::Message(text::render(message.value()))
+                                        }
+                                        None => {
+                                            problems
+                                                .entry(event.script.clone())
+                                                .or_default()
+                                                .insert("no message after the battle".to_owned());
+                                        }
                                     }
-                                    Some(text.to_ascii_lowercase().parse().ok()?)
                                 }
-
-                                get(t).unwrap_or_else(|| "placeholder".parse().unwrap())
+                                Err(problem) => {
+                                    problems
+                                        .entry(event.script.clone())
+                                        .or_default()
+                                        .insert(problem);
+                                }
                             }
-
-                            trainer = Some(NpcTrainer {
-                                group: get_group(t),
-                                character: Trainer {
-                                    party: party
-                                        .iter()
-                                        .flat_map(|p| {
-                                            let id = p.species[8..].replace('_', "-");
-                                            data.pokedex
-                                                .try_get_named(&id)
-                                                .map(|pokemon| {
-                                                    let mut saved = SavedPokemon {
-                                                        pokemon: pokemon.id,
-                                                        level: p.level,
-                                                        gender: None,
-                                                        ivs: StatSet::uniform(p.ivs / 6),
-                                                        ..Default::default()
-                                                    };
-                                                    if let Some(item) = &p.item {
-                                                        let id = item[5..].replace('_', " ");
-                                                        if let Some(item) = data
-                                                            .itemdex
-                                                            .try_get_named(&id)
-                                                            .or_else(|| {
-                                                                println!(
-                                                                    "Cannot get item id {}",
-                                                                    id
-                                                                );
-                                                                None
-                                                            })
-                                                        {
-                                                            saved.item = Some(item.id);
-                                                        }
-                                                    }
-                                                    if let Some(moves) = p.moves.as_ref() {
-                                                        for m in moves {
-                                                            let id = m[5..].replace('_', " ");
-                                                            if let Some(m) = data
-                                                                .movedex
-                                                                .try_get_named(&id)
-                                                                .or_else(|| {
-                                                                    if !id.eq_ignore_ascii_case(
-                                                                        "NONE",
-                                                                    ) {
-                                                                        println!(
-                                                                            "Cannot get move id {}",
-                                                                            id
-                                                                        );
-                                                                    }
-                                                                    None
-                                                                })
-                                                            {
-                                                                saved
-                                                                    .moves
-                                                                    .push(SavedMove::from(m.id));
-                                                            }
-                                                        }
-                                                    }
-                                                    saved
-                                                })
-                                                .or_else(|| {
-                                                    println!("Cannot get pokemon id {}", id);
-                                                    None
-                                                })
-                                        })
-                                        .collect(),
-                                    bag: Default::default(), //trainer.items.in,
-                                    worth: 0,
-                                },
-                                sight: match sight == 0 {
-                                    true => None,
-                                    false => Some(sight),
-                                },
-                                encounter: data.messages.get(encounter_id).unwrap().clone(),
-                                defeat: data.messages.get(defeat_id).unwrap().clone(),
-                                badge: None,
-                                disable: TrainerDisable::DisableSelf,
-                            });
-
-                            // if let Some(post) = script
-                            //     .commands
-                            //     .iter()
-                            //     .find(|command| command.command == "msgbox")
-                            // {
-                            //     let id = &post.arguments[0];
-                            //     let message = data.messages.get(id).unwrap();
-                            //     let message = message.value();
-                            //     interact = NpcInteract::Message(message.clone());
-                            // }
                         }
                     }
                 }
@@ -871,6 +1154,10 @@ fn into_world_npcs(mappings: &NameMappings, data: &ParsedData, events: &[JsonObj
 
                 let id = format!("npc_{}", index).parse().unwrap();
 
+                if let Some(info) = info {
+                    trainers.insert(id, info);
+                }
+
                 let group = group.parse().unwrap();
                 Some((
                     id,
@@ -918,6 +1205,108 @@ fn into_world_npcs(mappings: &NameMappings, data: &ParsedData, events: &[JsonObj
         .collect()
 }
 
+/// The trainer of an NPC battling with `trainerbattle_single` at `position` of its script,
+/// with the trainer's name, or why the NPC cannot be a trainer.
+#[allow(clippy::too_many_arguments)]
+fn npc_trainer(
+    mappings: &NameMappings,
+    data: &ParsedData,
+    constants: &Constants,
+    events: &[JsonObjectEvent],
+    index: usize,
+    event: &JsonObjectEvent,
+    script: &Script,
+    position: usize,
+    problems: &Problems,
+) -> Result<(Option<String>, NpcTrainer, TrainerInfo), String> {
+    let battle = &script.commands[position];
+    let (id, encounter_id, defeat_id) = match battle.arguments.as_slice() {
+        [id, encounter_id, defeat_id, ..] => (id, encounter_id, defeat_id),
+        _ => return Err(format!("{} is missing arguments", battle)),
+    };
+    // script run right after the player wins
+    let defeated = battle
+        .arguments
+        .get(3)
+        .and_then(|label| data.scripts.get(label));
+    let commands = script.commands.iter().chain(
+        defeated
+            .iter()
+            .flat_map(|defeated| defeated.commands.iter()),
+    );
+    let t = data
+        .trainers
+        .get(id)
+        .ok_or_else(|| format!("trainer {} is not defined", id))?;
+    let party = data
+        .parties
+        .get(&t.party)
+        .ok_or_else(|| format!("party {} of trainer {} is not defined", t.party, id))?;
+    let sight = event.trainer_sight_or_berry_tree_id.parse().map_err(|_| {
+        format!(
+            "sight {} of trainer {} is not a number",
+            event.trainer_sight_or_berry_tree_id, id
+        )
+    })?;
+    let message = |label: &String| {
+        data.messages
+            .get(label)
+            .map(|message| text::render(message.value()))
+            .ok_or_else(|| format!("text {} of trainer {} is not defined", label, id))
+    };
+
+    fn get_group(t: &script_parser::trainer::Trainer) -> TrainerGroupId {
+        fn get(t: &script_parser::trainer::Trainer) -> Option<TrainerGroupId> {
+            let text = t.pic.split_once("TRAINER_PIC_").map(|(.., r)| r)?;
+            let split = text.split_once('_')?;
+            if split.0.eq_ignore_ascii_case("RS") {
+                return None;
+            }
+            Some(text.to_ascii_lowercase().parse().ok()?)
+        }
+
+        get(t).unwrap_or_else(|| "placeholder".parse().unwrap())
+    }
+
+    let trainer = NpcTrainer {
+        group: get_group(t),
+        character: Trainer {
+            party: trainer::party_of(data, constants, t, party),
+            bag: Default::default(), //trainer.items.in,
+            worth: 0,
+        },
+        sight: match sight == 0 {
+            true => None,
+            false => Some(sight),
+        },
+        encounter: message(encounter_id)?,
+        defeat: message(defeat_id)?,
+        badge: trainer::badge(mappings, commands.clone()),
+        // npc ids rather than flags, so there is nothing for `numeric_ids` to replace
+        disable: match trainer::disables_gym(commands) {
+            true => TrainerDisable::Many(
+                events
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, e)| {
+                        *i != index
+                            && !e.trainer_type.eq_ignore_ascii_case("TRAINER_TYPE_NONE")
+                            && mappings.npcs.groups.contains_key(&e.graphics_id)
+                    })
+                    .map(|(i, ..)| format!("npc_{}", i).parse().unwrap())
+                    .collect(),
+            ),
+            false => TrainerDisable::DisableSelf,
+        },
+    };
+
+    Ok((
+        t.name.clone(),
+        trainer,
+        trainer::info(mappings, data, constants, id, t, problems, &event.script),
+    ))
+}
+
 fn into_world_objects(mappings: &NameMappings, events: &[JsonObjectEvent]) -> Objects {
     events
         .par_iter()
@@ -938,7 +1327,7 @@ fn into_world_objects(mappings: &NameMappings, events: &[JsonObjectEvent]) -> Ob
         .collect()
 }
 
-fn into_world_items(data: &ParsedData, events: &[JsonBgEvent]) -> Items {
+fn into_world_items(constants: &Constants, events: &[JsonBgEvent]) -> Items {
     events
         .par_iter()
         .filter(|event| event.type_ == "hidden_item")
@@ -950,23 +1339,7 @@ fn into_world_items(data: &ParsedData, events: &[JsonBgEvent]) -> Items {
                 },
                 ItemObject {
                     item: ItemStack {
-                        item: {
-                            let id = event.item.as_ref()?[5..]
-                                .to_ascii_lowercase()
-                                .parse()
-                                .ok()?;
-                            firecore_world_builder::world::pokedex::Dex::try_get(&data.itemdex, &id)
-                                .or_else(|| {
-                                    if !id.eq_ignore_ascii_case("NONE") {
-                                        println!(
-                                            "Cannot get item id {} for hidden item",
-                                            id.as_str()
-                                        );
-                                    }
-                                    None
-                                })?
-                                .id
-                        },
+                        item: constants.item(event.item.as_ref()?)?,
                         count: event.quantity?,
                     },
                     hidden: event.underfoot?,
@@ -987,7 +1360,7 @@ fn into_world_signs(data: &ParsedData, events: &[JsonBgEvent]) -> Signs {
                 .iter()
                 .find(|command| command.command == "msgbox")?;
             let id = msgbox.arguments.get(0)?;
-            let message = data.messages.get(id)?.clone();
+            let message = text::render(&data.messages.get(id)?);
             Some((
                 Coordinate {
                     x: event.x as _,
@@ -29,7 +29,8 @@ pub struct JsonMapData {
     pub connections: Option<Vec<JsonConnection>>,
     pub object_events: Vec<object::JsonObjectEvent>,
     pub warp_events: Vec<warp::JsonWarpEvent>,
-    // pub coord_events: Vec<>,
+    #[serde(default)]
+    pub coord_events: Vec<object::JsonCoordEvent>,
     pub bg_events: Vec<object::JsonBgEvent>,
 }
 
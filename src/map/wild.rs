@@ -1,14 +1,11 @@
-use std::sync::Arc;
-
 use hashbrown::HashMap;
 
-use firecore_world_builder::world::{
-    map::wild::{WildEntry, WildPokemon},
-    pokedex::{pokemon::Pokemon, BasicDex},
-};
+use firecore_world_builder::world::map::wild::{WildEntry, WildPokemon};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
+use crate::constants::Constants;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct JsonWildEncounters {
     pub wild_encounter_groups: Vec<JsonWildEncounterGroup>,
@@ -59,38 +56,19 @@ pub struct JsonWildPokemon {
 }
 
 impl JsonWildEncounterType {
-    pub fn into(self, pokedex: &BasicDex<Pokemon, Arc<Pokemon>>) -> WildEntry {
+    pub fn into(self, constants: &Constants) -> WildEntry {
         WildEntry {
             ratio: self.encounter_rate,
             encounters: self
                 .mons
                 .into_par_iter()
-                .flat_map(|mut p| {
-                    let species = &mut p.species[8..];
-                    unsafe {
-                        let find = '_' as u8;
-                        let replace = '-' as u8;
-                        species.as_bytes_mut().iter_mut().for_each(|u| {
-                            if *u == find {
-                                *u = replace;
-                            }
-                        })
-                    }
-                    pokedex.try_get_named(&species)
-                        .map(|species| WildPokemon {
-                            species: species.id,
-                            levels: p.min_level..=p.max_level,
-                        })
-                        .or_else(|| {
-                            eprintln!(
-                                "Could not get wild pokemon species {} because it does not exist!",
-                                p.species
-                            );
-                            None
-                        })
+                .flat_map(|p| {
+                    constants.species(&p.species).map(|species| WildPokemon {
+                        species,
+                        levels: p.min_level..=p.max_level,
+                    })
                 })
                 .collect(),
         }
     }
-
 }
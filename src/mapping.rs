@@ -21,6 +21,11 @@ pub struct NameMappings {
     pub npcs: NpcMappings,
     pub objects: ObjectMappings,
     pub audio: AudioMappings,
+    pub trainers: TrainerMappings,
+    pub constants: ConstantMappings,
+    pub scripts: ScriptMappings,
+    /// Special function of the decomp (`special`, `specialvar`) to the engine hook it calls
+    pub specials: HashMap<String, String>,
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -56,6 +61,64 @@ pub struct AudioMappings {
     pub sounds: HashMap<String, (SoundId, SoundVariant)>,
 }
 
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrainerMappings {
+    /// Encounter music (`TRAINER_ENCOUNTER_MUSIC_*`) to music id
+    pub music: HashMap<String, tinystr::TinyStr16>,
+    /// Trainer class (`CLASS_*`) to display name
+    pub classes: HashMap<String, String>,
+    /// Badge flag (`FLAG_BADGE0x_GET`) to badge id
+    pub badges: HashMap<String, tinystr::TinyStr16>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstantMappings {
+    /// Constant (`ITEM_*`, `SPECIES_*`, `MOVE_*`) to the name it has in the dex
+    pub aliases: HashMap<String, String>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptMappings {
+    /// Script command to the instruction it becomes, checked before the commands known by the compiler
+    pub commands: HashMap<String, CommandTemplate>,
+}
+
+/// An instruction made from a script command, such as
+/// `(instruction: "PlayFanfare", arguments: [Sound(0)])` for `playfanfare MUS_LEVEL_UP`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandTemplate {
+    /// Name of a `WorldInstruction` or `Instruction` variant
+    pub instruction: String,
+    /// Fields of the variant, in order
+    #[serde(default)]
+    pub arguments: Vec<TemplateArgument>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum TemplateArgument {
+    /// The command's argument at this position as written
+    Text(usize),
+    /// The command's argument at this position as a number
+    Int(usize),
+    /// An `ITEM_*` constant resolved into an item id
+    Item(usize),
+    /// A `SPECIES_*` constant resolved into a pokemon id
+    Species(usize),
+    /// A `MOVE_*` constant resolved into a move id
+    Move(usize),
+    /// A `MUS_*` or `SE_*` constant through the sound mappings, which fills two fields (sound and variant)
+    Sound(usize),
+    /// A `MAP_*` constant through the map id mappings
+    Map(usize),
+    /// A string such as `"Up"` for a direction
+    Literal(String),
+    Number(i64),
+}
+
 #[derive(Default, Deserialize, Serialize)]
 #[serde(transparent, deny_unknown_fields)]
 pub struct IdMappingsFrom {
@@ -86,4 +149,15 @@ impl From<IdMappingsFrom> for IdMappings {
                 .collect(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::NameMappings;
+
+    #[test]
+    fn mappings() {
+        if let Err(err) = ron::from_str::<NameMappings>(include_str!("../mappings.ron")) {
+            panic!("Cannot read mappings.ron: {}", err);
+        }
+    }
+}
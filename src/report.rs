@@ -0,0 +1,63 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::UnknownVariables;
+
+/// Everything found while compiling that needs a look, kept in a stable order so reports can be diffed between runs
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BuildReport {
+    /// `SPECIES_*`, `MOVE_*` and `ITEM_*` constants that could not be resolved into dex ids
+    pub unresolved: BTreeSet<String>,
+    /// Flags and vars used by scripts that are not in `flags.h` or `vars.h`
+    pub unknown: UnknownVariables,
+    /// Script commands that could not be converted
+    pub unsupported: BTreeMap<String, CommandCoverage>,
+    /// Special functions without an engine hook, with the scripts calling them, which keep the decomp's name
+    pub specials: BTreeMap<String, BTreeSet<String>>,
+    /// Sound effects without a mapping, with the scripts that play them, which are converted without the sound
+    pub sounds: BTreeMap<String, BTreeSet<String>>,
+    /// Problems in the jumps between scripts
+    pub flow: FlowReport,
+    /// Instructions and scripts the world's script engine has no equivalent for
+    pub lowering: LoweringReport,
+    /// Trainer scripts that could not be converted fully, with what is missing, where a trainer that cannot be built is left out
+    pub trainers: BTreeMap<String, BTreeSet<String>>,
+    /// Labels defined more than once, in one file or in several, with every file defining them.
+    /// The first definition of the first file is the one converted
+    pub duplicates: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CommandCoverage {
+    /// Times the command could not be converted
+    pub count: usize,
+    /// Scripts the command is in
+    pub scripts: BTreeSet<String>,
+    /// Why the command could not be converted, if it is known
+    pub errors: BTreeSet<String>,
+}
+
+/// Problems in the control flow of the converted scripts
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FlowReport {
+    /// Jump and call targets that are not a converted script, with the scripts jumping to them
+    pub dangling: BTreeMap<String, BTreeSet<String>>,
+    /// Scripts that cannot be reached from a map event or a `map_script` table
+    pub unreachable: BTreeSet<String>,
+    /// Scripts that can run past their last instruction without an `end`, `return` or `goto`,
+    /// which in the decomp fall through into the next label
+    pub falls_off: BTreeSet<String>,
+    /// Scripts that lock the player and can reach an `end` without a `release`
+    pub unreleased: BTreeSet<String>,
+}
+
+/// What [`crate::ScriptData::lower`] cannot express with the instructions of the world's script engine
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LoweringReport {
+    /// Instructions left out of the lowered scripts, by kind, with the scripts they are in
+    pub skipped: BTreeMap<String, BTreeSet<String>>,
+    /// Conditional jumps left out of their script, since the values their var takes are not known,
+    /// by script
+    pub branches: BTreeMap<String, BTreeSet<String>>,
+}
@@ -0,0 +1,225 @@
+use std::num::ParseIntError;
+
+use firecore_world_builder::world::{
+    audio::{SoundId, SoundVariant},
+    pokedex::{item::ItemId, moves::MoveId, pokemon::PokemonId},
+    positions::{Coordinate, Location},
+    script::ScriptId,
+};
+use script_parser::inc::Command;
+
+use crate::{constants::Constants, Defines, NameMappings};
+
+/// Checked access to the arguments of a command, where every error names the script, the command and the argument
+pub(crate) struct Args<'a> {
+    pub script: &'a ScriptId,
+    pub command: &'a Command,
+    /// Constants that numbers can be written as, see [`crate::ParsedData::script_constants`]
+    pub defines: &'a Defines,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(script: &'a ScriptId, command: &'a Command, defines: &'a Defines) -> Self {
+        Self {
+            script,
+            command,
+            defines,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.command.arguments.len()
+    }
+
+    pub fn get(&self, index: usize) -> Result<&'a str, InstructionError> {
+        self.command
+            .arguments
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| self.error(ErrorKind::MissingArgument(index)))
+    }
+
+    pub fn optional(&self, index: usize) -> Option<&'a str> {
+        self.command.arguments.get(index).map(String::as_str)
+    }
+
+    pub fn text(&self, index: usize) -> Result<String, InstructionError> {
+        self.get(index).map(str::to_owned)
+    }
+
+    /// A number, which may be hexadecimal, `TRUE`/`FALSE` or a constant such as `NO` or `MULTI_B_PRESSED`
+    pub fn int<T: TryFrom<i64>>(&self, index: usize) -> Result<T, InstructionError> {
+        self.parse_int(index, self.get(index)?)
+    }
+
+    pub fn parse_int<T: TryFrom<i64>>(
+        &self,
+        index: usize,
+        arg: &str,
+    ) -> Result<T, InstructionError> {
+        let value = match arg {
+            "TRUE" => 1,
+            "FALSE" => 0,
+            arg => match self.defines.get(arg) {
+                Some(value) => *value,
+                None => match arg.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => arg.parse(),
+                }
+                .map_err(|err| self.error(ErrorKind::ParseInt(index, arg.to_owned(), err)))?,
+            },
+        };
+        T::try_from(value).map_err(|_| self.error(ErrorKind::OutOfRange(index, arg.to_owned())))
+    }
+
+    pub fn coordinate(&self, x: usize, y: usize) -> Result<Coordinate, InstructionError> {
+        Ok(Coordinate {
+            x: self.int(x)?,
+            y: self.int(y)?,
+        })
+    }
+
+    pub fn item(&self, index: usize, constants: &Constants) -> Result<ItemId, InstructionError> {
+        let arg = self.get(index)?;
+        constants
+            .item(arg)
+            .ok_or_else(|| self.error(ErrorKind::MissingMapping(index, arg.to_owned())))
+    }
+
+    pub fn species(&self, index: usize, constants: &Constants) -> Result<PokemonId, InstructionError> {
+        let arg = self.get(index)?;
+        constants
+            .species(arg)
+            .ok_or_else(|| self.error(ErrorKind::MissingMapping(index, arg.to_owned())))
+    }
+
+    pub fn move_id(&self, index: usize, constants: &Constants) -> Result<MoveId, InstructionError> {
+        let arg = self.get(index)?;
+        constants
+            .move_id(arg)
+            .ok_or_else(|| self.error(ErrorKind::MissingMapping(index, arg.to_owned())))
+    }
+
+    /// A `MUS_*` or `SE_*` constant through the sound mappings
+    pub fn sound(
+        &self,
+        index: usize,
+        mappings: &NameMappings,
+    ) -> Result<(SoundId, SoundVariant), InstructionError> {
+        let arg = self.get(index)?;
+        let name = arg
+            .strip_prefix("MUS_")
+            .or_else(|| arg.strip_prefix("SE_"))
+            .unwrap_or(arg);
+        mappings
+            .audio
+            .sounds
+            .get(name)
+            .copied()
+            .ok_or_else(|| self.error(ErrorKind::MissingMapping(index, name.to_owned())))
+    }
+
+    /// A `MAP_*` constant through the map id mappings
+    pub fn location(&self, index: usize, mappings: &NameMappings) -> Result<Location, InstructionError> {
+        self.get(index).map(|map| crate::map_location(mappings, map))
+    }
+
+    pub fn error(&self, kind: ErrorKind) -> InstructionError {
+        InstructionError {
+            script: self.script.clone(),
+            command: self.command.command.clone(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InstructionError {
+    pub script: ScriptId,
+    pub command: String,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Unknown,
+    /// Arguments a command was given that are not in any of its forms
+    Arity(usize),
+    MissingArgument(usize),
+    ParseInt(usize, String, ParseIntError),
+    OutOfRange(usize, String),
+    MissingMapping(usize, String),
+    /// A command's template cannot be filled, with the reason
+    Template(String),
+}
+
+impl std::error::Error for InstructionError {}
+
+impl std::fmt::Display for InstructionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in script {}: ", self.command, self.script)?;
+        match &self.kind {
+            ErrorKind::Unknown => write!(f, "unknown command"),
+            ErrorKind::Arity(len) => write!(f, "cannot take {} arguments", len),
+            ErrorKind::MissingArgument(index) => write!(f, "missing argument {}", index),
+            ErrorKind::ParseInt(index, arg, err) => {
+                write!(f, "argument {} ({}) is not a number: {}", index, arg, err)
+            }
+            ErrorKind::OutOfRange(index, arg) => {
+                write!(f, "argument {} ({}) is out of range", index, arg)
+            }
+            ErrorKind::MissingMapping(index, arg) => {
+                write!(f, "argument {} ({}) has no mapping", index, arg)
+            }
+            ErrorKind::Template(reason) => write!(f, "template cannot be filled: {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use script_parser::inc::Command;
+
+    use crate::Defines;
+
+    use super::Args;
+
+    fn command(command: &str, arguments: &[&str]) -> Command {
+        Command {
+            command: command.to_owned(),
+            arguments: arguments.iter().map(|a| a.to_string()).collect(),
+            span: Default::default(),
+        }
+    }
+
+    #[test]
+    fn symbolic_ints() {
+        let defines = Defines::from_iter([("YES".to_owned(), 1), ("NO".to_owned(), 0)]);
+        let script = "Script".to_owned();
+
+        let compare = command("compare", &["VAR_RESULT", "NO"]);
+        let args = Args::new(&script, &compare, &defines);
+        assert_eq!(args.int::<u16>(1).unwrap(), 0);
+
+        let unknown = command("compare", &["VAR_RESULT", "MAYBE"]);
+        assert!(Args::new(&script, &unknown, &defines).int::<u16>(1).is_err());
+    }
+
+    #[test]
+    fn menu_constants() {
+        let defines = Defines::from_iter([("MULTI_B_PRESSED".to_owned(), 127)]);
+        let script = "Script".to_owned();
+
+        let case = command("case", &["MULTI_B_PRESSED", "Script_Cancel"]);
+        let args = Args::new(&script, &case, &defines);
+        assert_eq!(args.int::<u16>(0).unwrap(), 127);
+
+        let multichoice = command(
+            "multichoicedefault",
+            &["0", "0", "MULTICHOICE_YES_NO", "0x1", "FALSE"],
+        );
+        let args = Args::new(&script, &multichoice, &defines);
+        assert_eq!(args.int::<u8>(3).unwrap(), 1);
+        assert_eq!(args.int::<u8>(4).unwrap(), 0);
+    }
+}
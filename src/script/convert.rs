@@ -0,0 +1,399 @@
+use std::collections::BTreeSet;
+
+use dashmap::DashMap;
+
+use firecore_world_builder::world::{
+    positions::{Coordinate, Location},
+    script::{ScriptId, WorldInstruction},
+};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use script_parser::inc::Command;
+use serde_json::Value;
+
+use crate::{
+    constants::Constants, report::CommandCoverage, BuildReport, CommandTemplate, NameMappings, ParsedData,
+    TemplateArgument,
+};
+
+use super::{
+    args::{Args, ErrorKind, InstructionError},
+    movement::MovementStep,
+    Comparison, Instruction, Menu, ScriptData, WarpKind,
+};
+
+const SWITCH_VAR: &str = "VAR_0x8000";
+
+const WARP_ID_NONE: u8 = 0xFF;
+
+/// Names without a mapping that do not stop a command from converting, with the scripts using them
+#[derive(Default)]
+struct Unmapped {
+    sounds: DashMap<String, BTreeSet<ScriptId>>,
+    specials: DashMap<String, BTreeSet<ScriptId>>,
+}
+
+/// Converts every script, reporting the commands that could not be converted with the scripts they are in.
+///
+/// A script with such a command is left out unless `keep_unsupported` is set,
+/// in which case the command becomes an [`Instruction::Unsupported`].
+pub(crate) fn create_script_data(
+    mappings: &NameMappings,
+    constants: &Constants,
+    data: &ParsedData,
+    keep_unsupported: bool,
+    report: &mut BuildReport,
+) -> ScriptData {
+    let unsupported = DashMap::<String, CommandCoverage>::new();
+    let unmapped = Unmapped::default();
+
+    let data = ScriptData {
+        scripts: data
+            .scripts
+            .par_iter()
+            .flat_map(|r| {
+                let k = r.key();
+                let k = k.clone();
+                let v = r.value();
+                let mut blocked = false;
+                let mut instructions = Vec::with_capacity(v.commands.len());
+                for c in v.commands.iter() {
+                    match into_instruction(mappings, constants, data, &unmapped, &k, c) {
+                        Ok(i) => instructions.extend(i),
+                        Err(err) => {
+                            let mut coverage = unsupported.entry(c.command.clone()).or_default();
+                            coverage.count += 1;
+                            coverage.scripts.insert(k.clone());
+                            if !matches!(err.kind, ErrorKind::Unknown) {
+                                coverage.errors.insert(err.to_string());
+                            }
+                            blocked = true;
+                            instructions.push(Instruction::Unsupported(c.to_string()));
+                        }
+                    }
+                }
+                if blocked && !keep_unsupported {
+                    return None;
+                }
+                (!instructions.is_empty()).then(|| (k, instructions))
+            })
+            .collect(),
+        messages: data
+            .messages
+            .par_iter()
+            .map(|r| (r.key().clone(), r.value().clone()))
+            .collect(),
+    };
+
+    report.unsupported = unsupported.into_iter().collect();
+    report.specials = unmapped.specials.into_iter().collect();
+    report.sounds = unmapped.sounds.into_iter().collect();
+
+    data
+}
+
+/// Converts a command into instructions, through its template in the mappings if it has one.
+/// The three argument forms of `goto_if_*` and `call_if_*` become a comparison followed by the jump.
+fn into_instruction(
+    mappings: &NameMappings,
+    constants: &Constants,
+    data: &ParsedData,
+    unmapped: &Unmapped,
+    id: &ScriptId,
+    command: &Command,
+) -> Result<Vec<Instruction>, InstructionError> {
+    let args = Args::new(id, command, &data.script_constants);
+
+    if let Some(template) = mappings.scripts.commands.get(&command.command) {
+        return from_template(mappings, constants, unmapped, &args, template)
+            .map(|i| i.into_iter().collect());
+    }
+
+    // `goto_if_* dest` after a compare or `goto_if_* var, value, dest`
+    let conditional = |comparison: Comparison, call: bool| -> Result<Vec<Instruction>, InstructionError> {
+        let (compare, destination) = match args.len() {
+            1 => (None, args.text(0)?),
+            3 => (
+                Some(WorldInstruction::Compare(args.text(0)?, args.int(1)?)),
+                args.text(2)?,
+            ),
+            len => return Err(args.error(ErrorKind::Arity(len))),
+        };
+        let jump = match (call, comparison) {
+            (false, Comparison::Eq) => Instruction::World(WorldInstruction::GotoIfEq(destination)),
+            (false, comparison) => Instruction::GotoIf(comparison, destination),
+            (true, comparison) => Instruction::CallIf(comparison, destination),
+        };
+        Ok(compare.map(Instruction::World).into_iter().chain(std::iter::once(jump)).collect())
+    };
+
+    let instruction = match command.command.as_str() {
+        // set variables
+        "setvar" => WorldInstruction::SetVar(args.text(0)?, args.int(1)?),
+        "specialvar" => {
+            WorldInstruction::SpecialVar(args.text(0)?, special(mappings, unmapped, &args, 1)?)
+        }
+        "addvar" => return Ok(vec![Instruction::AddVar(args.text(0)?, args.int(1)?)]),
+        // compare
+        "compare" => WorldInstruction::Compare(args.text(0)?, args.int(1)?),
+        // goto/call
+        "call" => WorldInstruction::Call(args.text(0)?),
+        "goto" => return Ok(vec![Instruction::Goto(args.text(0)?)]),
+        "goto_if_lt" => return conditional(Comparison::Lt, false),
+        "goto_if_eq" => return conditional(Comparison::Eq, false),
+        "goto_if_gt" => return conditional(Comparison::Gt, false),
+        "goto_if_le" => return conditional(Comparison::Le, false),
+        "goto_if_ge" => return conditional(Comparison::Ge, false),
+        "goto_if_ne" => return conditional(Comparison::Ne, false),
+        "call_if_lt" => return conditional(Comparison::Lt, true),
+        "call_if_eq" => return conditional(Comparison::Eq, true),
+        "call_if_gt" => return conditional(Comparison::Gt, true),
+        "call_if_le" => return conditional(Comparison::Le, true),
+        "call_if_ge" => return conditional(Comparison::Ge, true),
+        "call_if_ne" => return conditional(Comparison::Ne, true),
+        "goto_if_set" => WorldInstruction::GotoIfSet(args.text(0)?, args.text(1)?),
+        "goto_if_unset" => return Ok(vec![Instruction::GotoIfUnset(args.text(0)?, args.text(1)?)]),
+        "call_if_set" => return Ok(vec![Instruction::CallIfSet(args.text(0)?, args.text(1)?)]),
+        "call_if_unset" => return Ok(vec![Instruction::CallIfUnset(args.text(0)?, args.text(1)?)]),
+        // Objects
+        "setobjectxy" => {
+            return Ok(vec![Instruction::SetObjectPosition(
+                args.text(0)?,
+                args.coordinate(1, 2)?,
+            )])
+        }
+        // applymovement LOCALID, Movement_Label[, MAP]
+        "applymovement" => {
+            let label = args.get(1)?;
+            let movement = data
+                .movements
+                .get(label)
+                .ok_or_else(|| args.error(ErrorKind::MissingMapping(1, label.to_owned())))?;
+            return Ok(vec![Instruction::ApplyMovement(
+                args.text(0)?,
+                label.to_owned(),
+                movement.steps.iter().map(MovementStep::from).collect(),
+            )]);
+        }
+        "waitmovement" => return Ok(vec![Instruction::WaitMovement(args.text(0)?)]),
+        // trainerbattle_single TRAINER, Text_Intro, Text_Defeat[, EventScript_Defeated]
+        "trainerbattle_single" => match args.len() {
+            3 | 4 => {
+                return Ok(vec![Instruction::TrainerBattleSingle(
+                    args.text(0)?,
+                    args.text(1)?,
+                    args.text(2)?,
+                    args.optional(3).map(str::to_owned),
+                )])
+            }
+            len => return Err(args.error(ErrorKind::Arity(len))),
+        },
+        // Message
+        "msgbox" => WorldInstruction::Msgbox(args.text(0)?, args.optional(1).map(str::to_owned)),
+        "textcolor" => WorldInstruction::TextColor(args.int(0)?),
+        "message" => WorldInstruction::Message(args.text(0)?),
+        // Menus, which leave the chosen option in `VAR_RESULT`
+        "yesnobox" => return Ok(vec![Instruction::YesNoBox]),
+        // multichoice x, y, MULTICHOICE_*, ignore b press
+        // multichoicedefault x, y, MULTICHOICE_*, default, ignore b press
+        "multichoice" | "multichoicedefault" => {
+            let menu = args.get(2)?;
+            let options = data
+                .menus
+                .get(menu)
+                .ok_or_else(|| args.error(ErrorKind::MissingMapping(2, menu.to_owned())))?;
+            let (default, ignore) = match args.len() {
+                4 => (0, args.int::<u8>(3)?),
+                5 => (args.int(3)?, args.int::<u8>(4)?),
+                len => return Err(args.error(ErrorKind::Arity(len))),
+            };
+            return Ok(vec![Instruction::Multichoice(Menu {
+                id: menu.to_owned(),
+                position: args.coordinate(0, 1)?,
+                options: options.clone(),
+                default,
+                cancel: ignore == 0,
+            })]);
+        }
+        // The switch macros keep the value in VAR_0x8000 to compare it against every case
+        "switch" => {
+            return Ok(vec![Instruction::CopyVar(SWITCH_VAR.to_owned(), args.text(0)?)])
+        }
+        "case" => {
+            return Ok(vec![
+                Instruction::World(WorldInstruction::Compare(SWITCH_VAR.to_owned(), args.int(0)?)),
+                Instruction::World(WorldInstruction::GotoIfEq(args.text(1)?)),
+            ])
+        }
+        // Item
+        "giveitem" | "finditem" | "additem" => {
+            let item = args.item(0, constants)?;
+            let count = match args.optional(1) {
+                Some(count) => args.parse_int(1, count)?,
+                None => 1,
+            };
+            return Ok(vec![match command.command.as_str() {
+                "giveitem" => Instruction::GiveItem(item, count),
+                "finditem" => Instruction::FindItem(item, count),
+                _ => Instruction::AddItem(item, count),
+            }]);
+        }
+        "checkitemspace" => WorldInstruction::CheckItemSpace(args.text(0)?, args.int(1)?),
+        "getitemname" => WorldInstruction::GetItemName(args.int(0)?, args.text(1)?),
+        // Warps
+        "warp" | "warpsilent" | "warpdoor" | "warphole" | "warpteleport" => {
+            let kind = match command.command.as_str() {
+                "warpsilent" => WarpKind::Silent,
+                "warpdoor" => WarpKind::Door,
+                "warphole" => WarpKind::Hole,
+                "warpteleport" => WarpKind::Teleport,
+                _ => WarpKind::Normal,
+            };
+            let (location, position) = warp_destination(mappings, data, &args)?;
+            return Ok(vec![Instruction::Warp(kind, location, position)]);
+        }
+        "setwarp" => {
+            let (location, position) = warp_destination(mappings, data, &args)?;
+            return Ok(vec![Instruction::SetWarp(location, position)]);
+        }
+        "setescapewarp" => {
+            let (location, position) = warp_destination(mappings, data, &args)?;
+            return Ok(vec![Instruction::SetEscapeWarp(location, position)]);
+        }
+        // Specials
+        "special" => {
+            return Ok(vec![Instruction::Special(special(mappings, unmapped, &args, 0)?)])
+        }
+        _ => return Err(args.error(ErrorKind::Unknown)),
+    };
+
+    Ok(vec![Instruction::World(instruction)])
+}
+
+/// Fills the fields of the template's variant, which is looked up in [`WorldInstruction`] first and [`Instruction`] second.
+///
+/// A command playing a sound effect without a mapping is left out, since the script works without it.
+fn from_template(
+    mappings: &NameMappings,
+    constants: &Constants,
+    unmapped: &Unmapped,
+    args: &Args,
+    template: &CommandTemplate,
+) -> Result<Option<Instruction>, InstructionError> {
+    let mut fields = Vec::with_capacity(template.arguments.len());
+
+    for argument in template.arguments.iter() {
+        let value = match argument {
+            TemplateArgument::Text(index) => Value::from(args.get(*index)?),
+            TemplateArgument::Int(index) => Value::from(args.int::<i64>(*index)?),
+            TemplateArgument::Item(index) => json(args, args.item(*index, constants)?)?,
+            TemplateArgument::Species(index) => json(args, args.species(*index, constants)?)?,
+            TemplateArgument::Move(index) => json(args, args.move_id(*index, constants)?)?,
+            TemplateArgument::Sound(index) => {
+                let (sound, variant) = match args.sound(*index, mappings) {
+                    Ok(sound) => sound,
+                    Err(..) if args.get(*index)?.starts_with("SE_") => {
+                        unmapped
+                            .sounds
+                            .entry(args.text(*index)?)
+                            .or_default()
+                            .insert(args.script.clone());
+                        return Ok(None);
+                    }
+                    Err(err) => return Err(err),
+                };
+                fields.push(json(args, sound)?);
+                json(args, variant)?
+            }
+            TemplateArgument::Map(index) => json(args, args.location(*index, mappings)?)?,
+            TemplateArgument::Literal(literal) => Value::from(literal.as_str()),
+            TemplateArgument::Number(number) => Value::from(*number),
+        };
+        fields.push(value);
+    }
+
+    let name = template.instruction.clone();
+
+    let value = match fields.len() {
+        0 => Value::String(name),
+        1 => Value::Object(std::iter::once((name, fields.remove(0))).collect()),
+        _ => Value::Object(std::iter::once((name, Value::Array(fields))).collect()),
+    };
+
+    serde_json::from_value::<WorldInstruction>(value.clone())
+        .map(Instruction::World)
+        .or_else(|_| serde_json::from_value::<Instruction>(value))
+        .map(Some)
+        .map_err(|err| args.error(ErrorKind::Template(err.to_string())))
+}
+
+/// A special function of the decomp through the specials mappings,
+/// keeping the decomp's name for one without a mapping.
+fn special(
+    mappings: &NameMappings,
+    unmapped: &Unmapped,
+    args: &Args,
+    index: usize,
+) -> Result<String, InstructionError> {
+    let special = args.get(index)?;
+    Ok(match mappings.specials.get(special) {
+        Some(hook) => hook.clone(),
+        None => {
+            unmapped
+                .specials
+                .entry(special.to_owned())
+                .or_default()
+                .insert(args.script.clone());
+            special.to_owned()
+        }
+    })
+}
+
+/// The destination of the `warp*` commands, resolved the same way as the destinations of map warps.
+///
+/// `warp MAP[, warp id][, x, y]`, where the position of the warp is used unless its id is `WARP_ID_NONE`,
+/// and no position keeps the position of the player.
+fn warp_destination(
+    mappings: &NameMappings,
+    data: &ParsedData,
+    args: &Args,
+) -> Result<(Location, Option<Coordinate>), InstructionError> {
+    let location = args.location(0, mappings)?;
+
+    let warp = |index: usize| -> Result<Option<Coordinate>, InstructionError> {
+        let id = match args.get(index)? {
+            "WARP_ID_NONE" => return Ok(None),
+            id => args.parse_int::<u8>(index, id)?,
+        };
+        if id == WARP_ID_NONE {
+            return Ok(None);
+        }
+        let map = args.get(0)?;
+        data.maps
+            .get(map)
+            .and_then(|map| {
+                map.data.warp_events.get(id as usize).map(|w| Coordinate {
+                    x: w.x as _,
+                    y: w.y as _,
+                })
+            })
+            .map(Some)
+            .ok_or_else(|| args.error(ErrorKind::MissingMapping(index, format!("{} of {}", id, map))))
+    };
+
+    let position = match args.len() {
+        1 => None,
+        2 => warp(1)?,
+        3 => Some(args.coordinate(1, 2)?),
+        4 => match warp(1)? {
+            Some(position) => Some(position),
+            None => Some(args.coordinate(2, 3)?),
+        },
+        len => return Err(args.error(ErrorKind::Arity(len))),
+    };
+
+    Ok((location, position))
+}
+
+fn json<T: serde::Serialize>(args: &Args, value: T) -> Result<Value, InstructionError> {
+    serde_json::to_value(value).map_err(|err| args.error(ErrorKind::Template(err.to_string())))
+}
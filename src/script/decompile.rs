@@ -0,0 +1,552 @@
+use std::fmt::Write;
+
+use hashbrown::HashMap;
+
+use firecore_world_builder::world::{
+    audio::{SoundId, SoundVariant},
+    pokedex::item::ItemId,
+    positions::{Coordinate, Direction, Location},
+    script::{ScriptId, WorldInstruction, WorldScriptData},
+};
+use script_parser::inc::Command;
+
+use crate::NameMappings;
+
+use super::{Comparison, Instruction, Menu, ScriptData, WarpKind};
+
+/// Writes converted scripts back as event scripts of the decomp, which [`script_parser::inc::parse`] reads,
+/// followed by their messages as `.string`s. Labels are sorted so two outputs can be diffed line by line.
+///
+/// Sounds, maps and specials are named through the mappings, items through their `ITEM_*` names,
+/// and instructions without a command of their own are written as `@` comments.
+pub fn decompile(
+    mappings: &NameMappings,
+    items: &HashMap<ItemId, String>,
+    scripts: &ScriptData,
+) -> String {
+    let mut source = String::new();
+
+    let mut ids = scripts.scripts.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let lines = scripts.scripts[id].iter().map(|instruction| {
+            command(mappings, items, instruction).ok_or_else(|| format!("{:?}", instruction))
+        });
+        write_script(&mut source, id, lines);
+    }
+
+    let mut ids = scripts.messages.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        write_message(&mut source, id, crate::text::source(&scripts.messages[id]));
+    }
+
+    source
+}
+
+/// Writes scripts lowered for the world back as event scripts, see [`decompile`]
+pub fn decompile_world(
+    mappings: &NameMappings,
+    items: &HashMap<ItemId, String>,
+    scripts: &WorldScriptData,
+) -> String {
+    let mut source = String::new();
+
+    let mut ids = scripts.scripts.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let lines = scripts.scripts[id].iter().map(|instruction| {
+            world(mappings, items, instruction).ok_or_else(|| format!("{:?}", instruction))
+        });
+        write_script(&mut source, id, lines);
+    }
+
+    let mut ids = scripts.messages.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        write_pages(&mut source, id, &scripts.messages[id]);
+    }
+
+    source
+}
+
+fn write_script(
+    source: &mut String,
+    id: &ScriptId,
+    lines: impl Iterator<Item = Result<Command, String>>,
+) {
+    let _ = writeln!(source, "{}::", id);
+    for line in lines {
+        let _ = match line {
+            Ok(command) => writeln!(source, "\t{}", command),
+            Err(instruction) => writeln!(source, "\t@ {}", instruction),
+        };
+    }
+    source.push('\n');
+}
+
+/// Decoded text, with every break and control code as it was written
+fn write_message(source: &mut String, id: &str, lines: Vec<String>) {
+    let _ = writeln!(source, "{}::", id);
+    for line in lines {
+        let _ = writeln!(source, "\t.string \"{}\"", line);
+    }
+    source.push('\n');
+}
+
+/// Pages of the world's messages become `\p` and their lines `\n`, the player's name `{PLAYER}`
+fn write_pages(source: &mut String, id: &str, pages: &[Vec<String>]) {
+    let _ = writeln!(source, "{}::", id);
+    let lines = pages.iter().enumerate().flat_map(|(page, lines)| {
+        let last = lines.len().saturating_sub(1);
+        lines.iter().enumerate().map(move |(line, text)| {
+            match (line == last, page + 1 == pages.len()) {
+                (true, true) => (text, "$"),
+                (true, false) => (text, "\\p"),
+                (false, _) => (text, "\\n"),
+            }
+        })
+    });
+    for (text, end) in lines {
+        let text = text.replace('"', "\\\"").replace("%p", "{PLAYER}");
+        let _ = writeln!(source, "\t.string \"{}{}\"", text, end);
+    }
+    source.push('\n');
+}
+
+fn line(command: &str, arguments: impl IntoIterator<Item = String>) -> Command {
+    Command {
+        command: command.to_owned(),
+        arguments: arguments.into_iter().collect(),
+        span: Default::default(),
+    }
+}
+
+fn world(
+    mappings: &NameMappings,
+    items: &HashMap<ItemId, String>,
+    instruction: &WorldInstruction,
+) -> Option<Command> {
+    Some(match instruction {
+        WorldInstruction::End => line("end", []),
+        WorldInstruction::Return => line("return", []),
+        WorldInstruction::SetVar(var, value) => line("setvar", [var.clone(), value.to_string()]),
+        WorldInstruction::SetFlag(flag) => line("setflag", [flag.clone()]),
+        WorldInstruction::SpecialVar(var, special) => line(
+            "specialvar",
+            [var.clone(), self::special(mappings, special)],
+        ),
+        WorldInstruction::Compare(var, value) => line("compare", [var.clone(), value.to_string()]),
+        WorldInstruction::Call(target) => line("call", [target.clone()]),
+        WorldInstruction::GotoIfEq(target) => line("goto_if_eq", [target.clone()]),
+        WorldInstruction::GotoIfSet(flag, target) => {
+            line("goto_if_set", [flag.clone(), target.clone()])
+        }
+        WorldInstruction::Lock => line("lock", []),
+        WorldInstruction::Release => line("release", []),
+        WorldInstruction::FacePlayer => line("faceplayer", []),
+        WorldInstruction::Walk(direction) => {
+            line(&format!("walk_{}", self::direction(*direction)), [])
+        }
+        WorldInstruction::Msgbox(message, kind) => line(
+            "msgbox",
+            std::iter::once(message.clone()).chain(kind.clone()),
+        ),
+        WorldInstruction::TextColor(color) => line("textcolor", [color.to_string()]),
+        WorldInstruction::Message(message) => line("message", [message.clone()]),
+        WorldInstruction::WaitMessage => line("waitmessage", []),
+        WorldInstruction::PlayFanfare(sound, variant) => line(
+            "playfanfare",
+            [self::sound(mappings, "MUS_", sound, variant)?],
+        ),
+        WorldInstruction::AddItem(item) => line("additem", [self::item(items, item)]),
+        WorldInstruction::CheckItemSpace(item, count) => {
+            line("checkitemspace", [item.clone(), count.to_string()])
+        }
+        WorldInstruction::GetItemName(index, item) => {
+            line("getitemname", [index.to_string(), item.clone()])
+        }
+        _ => return None,
+    })
+}
+
+fn command(
+    mappings: &NameMappings,
+    items: &HashMap<ItemId, String>,
+    instruction: &Instruction,
+) -> Option<Command> {
+    let conditional = |kind: &str, comparison: &Comparison, target: &ScriptId| {
+        let comparison = match comparison {
+            Comparison::Lt => "lt",
+            Comparison::Eq => "eq",
+            Comparison::Gt => "gt",
+            Comparison::Le => "le",
+            Comparison::Ge => "ge",
+            Comparison::Ne => "ne",
+        };
+        line(&format!("{}_if_{}", kind, comparison), [target.clone()])
+    };
+
+    Some(match instruction {
+        Instruction::World(instruction) => return world(mappings, items, instruction),
+        Instruction::Goto(target) => line("goto", [target.clone()]),
+        Instruction::GotoIf(comparison, target) => conditional("goto", comparison, target),
+        Instruction::GotoIfUnset(flag, target) => {
+            line("goto_if_unset", [flag.clone(), target.clone()])
+        }
+        Instruction::CallIf(comparison, target) => conditional("call", comparison, target),
+        Instruction::CallIfSet(flag, target) => line("call_if_set", [flag.clone(), target.clone()]),
+        Instruction::CallIfUnset(flag, target) => {
+            line("call_if_unset", [flag.clone(), target.clone()])
+        }
+        Instruction::CheckFlag(flag) => line("checkflag", [flag.clone()]),
+        Instruction::ClearFlag(flag) => line("clearflag", [flag.clone()]),
+        Instruction::AddVar(var, value) => line("addvar", [var.clone(), value.to_string()]),
+        Instruction::CopyVar(destination, source) => {
+            line("copyvar", [destination.clone(), source.clone()])
+        }
+        Instruction::Delay(frames) => line("delay", [frames.to_string()]),
+        Instruction::PlaySound(sound, variant) => {
+            line("playse", [self::sound(mappings, "SE_", sound, variant)?])
+        }
+        Instruction::WaitSound => line("waitse", []),
+        Instruction::CloseMessage => line("closemessage", []),
+        Instruction::YesNoBox => line("yesnobox", []),
+        Instruction::GiveItem(item, count) => {
+            line("giveitem", [self::item(items, item), count.to_string()])
+        }
+        Instruction::FindItem(item, count) => {
+            line("finditem", [self::item(items, item), count.to_string()])
+        }
+        Instruction::AddItem(item, count) => {
+            line("additem", [self::item(items, item), count.to_string()])
+        }
+        Instruction::SetObjectPosition(object, position) => line(
+            "setobjectxy",
+            [
+                object.clone(),
+                position.x.to_string(),
+                position.y.to_string(),
+            ],
+        ),
+        Instruction::RemoveObject(object) => line("removeobject", [object.clone()]),
+        Instruction::AddObject(object) => line("addobject", [object.clone()]),
+        Instruction::WaitMovement(object) => line("waitmovement", [object.clone()]),
+        Instruction::Warp(kind, location, position) => {
+            let command = match kind {
+                WarpKind::Normal => "warp",
+                WarpKind::Silent => "warpsilent",
+                WarpKind::Door => "warpdoor",
+                WarpKind::Hole => "warphole",
+                WarpKind::Teleport => "warpteleport",
+            };
+            line(command, destination(mappings, location, position))
+        }
+        Instruction::SetWarp(location, position) => {
+            line("setwarp", destination(mappings, location, position))
+        }
+        Instruction::SetEscapeWarp(location, position) => {
+            line("setescapewarp", destination(mappings, location, position))
+        }
+        Instruction::Special(special) => line("special", [self::special(mappings, special)]),
+        Instruction::Multichoice(Menu {
+            id,
+            position,
+            default,
+            cancel,
+            ..
+        }) => {
+            let position = [position.x.to_string(), position.y.to_string()];
+            let ignore = (!cancel as u8).to_string();
+            match *default {
+                0 => line(
+                    "multichoice",
+                    position.into_iter().chain([id.clone(), ignore]),
+                ),
+                default => line(
+                    "multichoicedefault",
+                    position
+                        .into_iter()
+                        .chain([id.clone(), default.to_string(), ignore]),
+                ),
+            }
+        }
+        Instruction::TrainerBattleSingle(trainer, intro, defeat, defeated) => line(
+            "trainerbattle_single",
+            [trainer.clone(), intro.clone(), defeat.clone()]
+                .into_iter()
+                .chain(defeated.clone()),
+        ),
+        Instruction::ApplyMovement(object, label, ..) => {
+            line("applymovement", [object.clone(), label.clone()])
+        }
+        Instruction::Unsupported(..) => return None,
+    })
+}
+
+fn direction(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Down => "down",
+        Direction::Up => "up",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+/// The `MUS_*` or `SE_*` name of a sound
+fn sound(
+    mappings: &NameMappings,
+    prefix: &str,
+    sound: &SoundId,
+    variant: &SoundVariant,
+) -> Option<String> {
+    mappings
+        .audio
+        .sounds
+        .iter()
+        .find(|(.., mapped)| mapped.0 == *sound && mapped.1 == *variant)
+        .map(|(name, ..)| format!("{}{}", prefix, name))
+}
+
+/// The `ITEM_*` name of an item, or its id without one
+fn item(items: &HashMap<ItemId, String>, item: &ItemId) -> String {
+    items.get(item).cloned().unwrap_or_else(|| item.to_string())
+}
+
+/// The decomp's special for an engine hook
+fn special(mappings: &NameMappings, hook: &str) -> String {
+    mappings
+        .specials
+        .iter()
+        .find(|(.., mapped)| mapped.as_str() == hook)
+        .map(|(special, ..)| special.clone())
+        .unwrap_or_else(|| hook.to_owned())
+}
+
+/// `MAP_*[, x, y]`
+fn destination(
+    mappings: &NameMappings,
+    location: &Location,
+    position: &Option<Coordinate>,
+) -> Vec<String> {
+    let map = mappings
+        .map
+        .id
+        .iter()
+        .find(|(.., mapped)| *mapped == location)
+        .map(|(map, ..)| map.clone())
+        .unwrap_or_else(|| format!("MAP_{}", location.index));
+    std::iter::once(map)
+        .chain(
+            position
+                .iter()
+                .flat_map(|position| [position.x.to_string(), position.y.to_string()]),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use firecore_world_builder::world::{
+        pokedex::item::ItemId, positions::Coordinate, script::WorldInstruction,
+    };
+    use hashbrown::HashMap;
+
+    use super::decompile;
+    use crate::{Comparison, Instruction, Menu, NameMappings, ScriptData};
+
+    #[test]
+    fn round_trip() {
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "PalletTown_EventScript_Sign".to_owned(),
+            vec![
+                Instruction::World(WorldInstruction::Lock),
+                Instruction::World(WorldInstruction::Compare(
+                    "VAR_MAP_SCENE_PALLET_TOWN".to_owned(),
+                    2,
+                )),
+                Instruction::GotoIf(Comparison::Ge, "PalletTown_EventScript_After".to_owned()),
+                Instruction::World(WorldInstruction::Msgbox(
+                    "PalletTown_Text_Sign".to_owned(),
+                    Some("MSGBOX_SIGN".to_owned()),
+                )),
+                Instruction::World(WorldInstruction::Release),
+                Instruction::World(WorldInstruction::End),
+            ],
+        );
+
+        let source = decompile(&NameMappings::default(), &Default::default(), &scripts);
+
+        let (parsed, diagnostics) = script_parser::inc::parse(&source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].name, "PalletTown_EventScript_Sign");
+        assert_eq!(
+            parsed[0]
+                .commands
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "lock",
+                "compare VAR_MAP_SCENE_PALLET_TOWN, 2",
+                "goto_if_ge PalletTown_EventScript_After",
+                "msgbox PalletTown_Text_Sign, MSGBOX_SIGN",
+                "release",
+                "end",
+            ]
+        );
+    }
+    #[test]
+    fn item_names() {
+        let potion: ItemId = "potion".parse().unwrap();
+        let items = HashMap::from_iter([(potion, "ITEM_POTION".to_owned())]);
+
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "Route1_EventScript_Potion".to_owned(),
+            vec![
+                Instruction::GiveItem(potion, 1),
+                Instruction::AddItem(potion, 5),
+                Instruction::World(WorldInstruction::End),
+            ],
+        );
+
+        let source = decompile(&NameMappings::default(), &items, &scripts);
+
+        let (parsed, diagnostics) = script_parser::inc::parse(&source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            parsed[0]
+                .commands
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["giveitem ITEM_POTION, 1", "additem ITEM_POTION, 5", "end"]
+        );
+    }
+    #[test]
+    fn menus_and_movements() {
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "PalletTown_EventScript_Oak".to_owned(),
+            vec![
+                Instruction::ApplyMovement(
+                    "LOCALID_OAK".to_owned(),
+                    "PalletTown_Movement_OakWalk".to_owned(),
+                    Vec::new(),
+                ),
+                Instruction::Multichoice(Menu {
+                    id: "MULTICHOICE_YES_NO".to_owned(),
+                    position: Coordinate { x: 20, y: 8 },
+                    options: vec!["YES".to_owned(), "NO".to_owned()],
+                    default: 1,
+                    cancel: true,
+                }),
+                Instruction::World(WorldInstruction::End),
+            ],
+        );
+
+        let source = decompile(&NameMappings::default(), &Default::default(), &scripts);
+
+        let (parsed, diagnostics) = script_parser::inc::parse(&source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            parsed[0]
+                .commands
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "applymovement LOCALID_OAK, PalletTown_Movement_OakWalk",
+                "multichoicedefault 20, 8, MULTICHOICE_YES_NO, 1, 0",
+                "end",
+            ]
+        );
+    }
+    #[test]
+    fn trainer_scripts() {
+        let source = "Route3_EventScript_Ben::
+\ttrainerbattle_single TRAINER_YOUNGSTER_BEN, Route3_Text_BenIntro, Route3_Text_BenDefeat, Route3_EventScript_BenDefeated
+\tmsgbox Route3_Text_BenPostBattle, MSGBOX_AUTOCLOSE
+\tend
+
+Route3_EventScript_BenDefeated::
+\tsetflag FLAG_BEAT_BEN
+\trelease
+\tend
+
+";
+
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "Route3_EventScript_Ben".to_owned(),
+            vec![
+                Instruction::TrainerBattleSingle(
+                    "TRAINER_YOUNGSTER_BEN".to_owned(),
+                    "Route3_Text_BenIntro".to_owned(),
+                    "Route3_Text_BenDefeat".to_owned(),
+                    Some("Route3_EventScript_BenDefeated".to_owned()),
+                ),
+                Instruction::World(WorldInstruction::Msgbox(
+                    "Route3_Text_BenPostBattle".to_owned(),
+                    Some("MSGBOX_AUTOCLOSE".to_owned()),
+                )),
+                Instruction::World(WorldInstruction::End),
+            ],
+        );
+        scripts.scripts.insert(
+            "Route3_EventScript_BenDefeated".to_owned(),
+            vec![
+                Instruction::World(WorldInstruction::SetFlag("FLAG_BEAT_BEN".to_owned())),
+                Instruction::World(WorldInstruction::Release),
+                Instruction::World(WorldInstruction::End),
+            ],
+        );
+
+        // written the way the decomp writes it, so the two can be diffed line by line
+        assert_eq!(
+            decompile(&NameMappings::default(), &Default::default(), &scripts),
+            source
+        );
+    }
+    #[test]
+    fn messages() {
+        let source = "PalletTown_Text_OakLetsGo::
+\t.string \"OAK: {PLAYER}!\\n\"
+\t.string \"{COLOR RED}Hold it!{PAUSE 30}\\l\"
+\t.string \"Let's go, \\\"champ\\\"!{PLAY_SE SE_DING_DONG}\\p\"
+\t.string \"{PAUSE_UNTIL_PRESS}{UNKNOWN_CODE 1}$\"
+
+";
+        let charmap = script_parser::text::Charmap::parse(
+            "PLAYER = FD 01
+COLOR = FC 01
+PAUSE = FC 08
+PAUSE_UNTIL_PRESS = FC 09
+PLAY_SE = FC 10
+",
+        );
+
+        let (parsed, diagnostics) = script_parser::inc::parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let message = script_parser::inc::Message::from_script(&parsed[0]).unwrap();
+
+        let mut scripts = ScriptData::default();
+        scripts.messages.insert(
+            message.name,
+            message
+                .text
+                .iter()
+                .map(|line| line.map(|text| charmap.decode(text).0))
+                .collect(),
+        );
+
+        // scrolls and control codes come back as they were written
+        assert_eq!(
+            decompile(&NameMappings::default(), &Default::default(), &scripts),
+            source
+        );
+    }
+}
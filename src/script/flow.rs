@@ -0,0 +1,363 @@
+use std::collections::BTreeSet;
+
+use firecore_world_builder::world::script::{ScriptId, WorldInstruction};
+use hashbrown::{HashMap, HashSet};
+use script_parser::inc::Script;
+
+use crate::{report::FlowReport, Instruction, ParsedData, ScriptData};
+
+/// How an instruction leaves the straight line of its script
+enum Jump<'a> {
+    /// Never comes back, `goto`
+    Goto(&'a ScriptId),
+    /// Jumps only if a condition holds
+    Branch(&'a ScriptId),
+    /// Comes back after the script returns
+    Call(&'a ScriptId),
+    /// `end` or `return`
+    Stop,
+}
+
+fn jump(instruction: &Instruction) -> Option<Jump> {
+    Some(match instruction {
+        Instruction::Goto(target) => Jump::Goto(target),
+        Instruction::GotoIf(.., target)
+        | Instruction::GotoIfUnset(.., target)
+        | Instruction::TrainerBattleSingle(.., Some(target))
+        | Instruction::World(
+            WorldInstruction::GotoIfEq(target) | WorldInstruction::GotoIfSet(.., target),
+        ) => Jump::Branch(target),
+        Instruction::CallIf(.., target)
+        | Instruction::CallIfSet(.., target)
+        | Instruction::CallIfUnset(.., target)
+        | Instruction::World(WorldInstruction::Call(target)) => Jump::Call(target),
+        Instruction::World(WorldInstruction::End | WorldInstruction::Return) => Jump::Stop,
+        _ => return None,
+    })
+}
+
+/// Checks the jumps between converted scripts, how every script ends and that locks are released.
+///
+/// Scripts are entered from the object, bg and coord events of maps and from the `map_script` tables,
+/// and reached from there through their jumps and calls and the scripts trainer battles continue with.
+pub(crate) fn analyze(scripts: &ScriptData, data: &ParsedData) -> FlowReport {
+    let mut entries = Vec::new();
+
+    for map in data.maps.iter() {
+        let map = &map.value().data;
+        entries.extend(map.object_events.iter().map(|event| event.script.clone()));
+        entries.extend(map.bg_events.iter().flat_map(|event| event.script.clone()));
+        entries.extend(map.coord_events.iter().flat_map(|event| event.script.clone()));
+    }
+
+    for script in data.scripts.iter() {
+        entries.extend(map_scripts(script.value()));
+    }
+
+    let parsed = data
+        .scripts
+        .iter()
+        .map(|script| {
+            let converted = scripts.scripts.contains_key(script.key());
+            (script.key().clone(), parsed_jumps(script.value(), converted))
+        })
+        .collect();
+
+    check(scripts, entries, &parsed)
+}
+
+/// The scripts of a `map_script` table, `map_script TYPE, Script` or `map_script_2 VAR, value, Script`
+fn map_scripts(script: &Script) -> impl Iterator<Item = ScriptId> + '_ {
+    script
+        .commands
+        .iter()
+        .flat_map(|command| match command.command.as_str() {
+            "map_script" => command.arguments.get(1),
+            "map_script_2" => command.arguments.get(2),
+            _ => None,
+        })
+        .cloned()
+}
+
+/// Jumps of a parsed script that its instructions do not keep: the script a double battle continues with
+/// after a win and, for a script that was not converted, every argument that may be a label
+fn parsed_jumps(script: &Script, converted: bool) -> Vec<ScriptId> {
+    script
+        .commands
+        .iter()
+        .flat_map(|command| match (command.command.as_str(), converted) {
+            ("trainerbattle_double", ..) => command.arguments.get(4..),
+            (.., false) => Some(command.arguments.as_slice()),
+            _ => None,
+        })
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+fn check(
+    scripts: &ScriptData,
+    entries: Vec<ScriptId>,
+    parsed: &HashMap<ScriptId, Vec<ScriptId>>,
+) -> FlowReport {
+    let mut report = FlowReport::default();
+
+    for (id, instructions) in scripts.scripts.iter() {
+        let mut stops = false;
+        for instruction in instructions {
+            let (target, goto) = match jump(instruction) {
+                Some(Jump::Goto(target)) => (target, true),
+                Some(Jump::Branch(target) | Jump::Call(target)) => (target, false),
+                Some(Jump::Stop) => {
+                    stops = true;
+                    break;
+                }
+                None => continue,
+            };
+            if !scripts.scripts.contains_key(target) {
+                report
+                    .dangling
+                    .entry(target.clone())
+                    .or_default()
+                    .insert(id.clone());
+            }
+            if goto {
+                stops = true;
+                break;
+            }
+        }
+        if !stops {
+            report.falls_off.insert(id.clone());
+        }
+    }
+
+    let reached = reach(scripts, entries, parsed);
+
+    report.unreachable = scripts
+        .scripts
+        .keys()
+        .filter(|id| !reached.contains(id.as_str()))
+        .cloned()
+        .collect();
+
+    report.unreleased = unreleased(scripts);
+
+    report
+}
+
+/// Every label reached from the entries through the jumps of converted scripts and the jumps of parsed scripts
+fn reach(
+    scripts: &ScriptData,
+    entries: Vec<ScriptId>,
+    parsed: &HashMap<ScriptId, Vec<ScriptId>>,
+) -> HashSet<ScriptId> {
+    let mut reached = HashSet::new();
+    let mut stack = entries;
+
+    while let Some(id) = stack.pop() {
+        if reached.contains(&id) {
+            continue;
+        }
+        for instruction in scripts.scripts.get(&id).into_iter().flatten() {
+            match jump(instruction) {
+                Some(Jump::Goto(target)) => {
+                    stack.push(target.clone());
+                    break;
+                }
+                Some(Jump::Branch(target) | Jump::Call(target)) => stack.push(target.clone()),
+                Some(Jump::Stop) => break,
+                None => (),
+            }
+        }
+        stack.extend(parsed.get(&id).into_iter().flatten().cloned());
+        reached.insert(id);
+    }
+
+    reached
+}
+
+/// Follows every script with the lock it runs under, finding the scripts that lock the player
+/// and can reach an `end` without a `release`.
+///
+/// Called scripts are assumed to keep the lock as it was.
+fn unreleased(scripts: &ScriptData) -> BTreeSet<ScriptId> {
+    let mut unreleased = BTreeSet::new();
+    // a script with the script that locked before it, if any
+    let mut visited = HashSet::new();
+    let mut stack = scripts
+        .scripts
+        .keys()
+        .map(|id| (id, None))
+        .collect::<Vec<(&ScriptId, Option<&ScriptId>)>>();
+
+    while let Some((id, mut locked)) = stack.pop() {
+        if !visited.insert((id, locked)) {
+            continue;
+        }
+        let instructions = match scripts.scripts.get(id) {
+            Some(instructions) => instructions,
+            None => continue,
+        };
+        for instruction in instructions {
+            match instruction {
+                Instruction::World(WorldInstruction::Lock) => {
+                    locked.get_or_insert(id);
+                }
+                Instruction::World(WorldInstruction::Release) => locked = None,
+                Instruction::World(WorldInstruction::End) => {
+                    unreleased.extend(locked.cloned());
+                    break;
+                }
+                instruction => match jump(instruction) {
+                    Some(Jump::Goto(target)) => {
+                        stack.push((target, locked));
+                        break;
+                    }
+                    Some(Jump::Branch(target)) => stack.push((target, locked)),
+                    Some(Jump::Stop) => break,
+                    Some(Jump::Call(..)) | None => (),
+                },
+            }
+        }
+    }
+
+    unreleased
+}
+
+#[cfg(test)]
+mod tests {
+    use firecore_world_builder::world::script::WorldInstruction;
+    use hashbrown::HashMap;
+    use script_parser::inc;
+
+    use crate::{report::FlowReport, Instruction, ScriptData};
+
+    fn world(instruction: WorldInstruction) -> Instruction {
+        Instruction::World(instruction)
+    }
+
+    /// Checks converted scripts with their parsed source, entering them from `entries`
+    fn check(source: &str, scripts: &ScriptData, entries: &[&str]) -> FlowReport {
+        let (parsed, diagnostics) = inc::parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let entries = entries
+            .iter()
+            .map(|entry| entry.to_string())
+            .chain(parsed.iter().flat_map(super::map_scripts))
+            .collect();
+        let parsed = parsed
+            .iter()
+            .map(|script| {
+                let converted = scripts.scripts.contains_key(&script.name);
+                (script.name.clone(), super::parsed_jumps(script, converted))
+            })
+            .collect::<HashMap<_, _>>();
+        super::check(scripts, entries, &parsed)
+    }
+
+    #[test]
+    fn trainer_scripts() {
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "Route3_EventScript_Ben".to_owned(),
+            vec![
+                Instruction::TrainerBattleSingle(
+                    "TRAINER_YOUNGSTER_BEN".to_owned(),
+                    "Route3_Text_BenIntro".to_owned(),
+                    "Route3_Text_BenDefeat".to_owned(),
+                    Some("Route3_EventScript_BenDefeated".to_owned()),
+                ),
+                world(WorldInstruction::Msgbox(
+                    "Route3_Text_BenPostBattle".to_owned(),
+                    Some("MSGBOX_AUTOCLOSE".to_owned()),
+                )),
+                world(WorldInstruction::End),
+            ],
+        );
+        scripts.scripts.insert(
+            "Route3_EventScript_BenDefeated".to_owned(),
+            vec![
+                world(WorldInstruction::SetFlag("FLAG_BEAT_BEN".to_owned())),
+                world(WorldInstruction::Release),
+                world(WorldInstruction::End),
+            ],
+        );
+
+        let report = check(
+            "Route3_EventScript_Ben::
+    trainerbattle_single TRAINER_YOUNGSTER_BEN, Route3_Text_BenIntro, Route3_Text_BenDefeat, Route3_EventScript_BenDefeated
+    msgbox Route3_Text_BenPostBattle, MSGBOX_AUTOCLOSE
+    end
+Route3_EventScript_BenDefeated::
+    setflag FLAG_BEAT_BEN
+    release
+    end
+",
+            &scripts,
+            // the trainer's object event
+            &["Route3_EventScript_Ben"],
+        );
+        assert!(report.unreachable.is_empty(), "{:?}", report.unreachable);
+        assert!(report.dangling.is_empty(), "{:?}", report.dangling);
+    }
+
+    #[test]
+    fn called_only_by_unreachable_scripts() {
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "PalletTown_OnTransition".to_owned(),
+            vec![
+                world(WorldInstruction::SetFlag("FLAG_VISITED_PALLET_TOWN".to_owned())),
+                world(WorldInstruction::End),
+            ],
+        );
+        scripts.scripts.insert(
+            "PalletTown_EventScript_Sign".to_owned(),
+            vec![
+                world(WorldInstruction::Msgbox(
+                    "PalletTown_Text_Sign".to_owned(),
+                    Some("MSGBOX_SIGN".to_owned()),
+                )),
+                world(WorldInstruction::End),
+            ],
+        );
+        // B, which nothing enters, calls A
+        scripts.scripts.insert(
+            "PalletTown_EventScript_B".to_owned(),
+            vec![
+                world(WorldInstruction::Call("PalletTown_EventScript_A".to_owned())),
+                world(WorldInstruction::End),
+            ],
+        );
+        scripts.scripts.insert(
+            "PalletTown_EventScript_A".to_owned(),
+            vec![
+                world(WorldInstruction::SetVar("VAR_TEMP_1".to_owned(), 1)),
+                world(WorldInstruction::Return),
+            ],
+        );
+
+        let report = check(
+            "PalletTown_MapScripts::
+    map_script MAP_SCRIPT_ON_TRANSITION, PalletTown_OnTransition
+    .byte 0
+PalletTown_OnTransition::
+    setflag FLAG_VISITED_PALLET_TOWN
+    end
+PalletTown_EventScript_B::
+    call PalletTown_EventScript_A
+    end
+PalletTown_EventScript_A::
+    setvar VAR_TEMP_1, 1
+    return
+",
+            &scripts,
+            // the sign's bg event
+            &["PalletTown_EventScript_Sign"],
+        );
+        assert_eq!(
+            report.unreachable.iter().collect::<Vec<_>>(),
+            ["PalletTown_EventScript_A", "PalletTown_EventScript_B"]
+        );
+    }
+}
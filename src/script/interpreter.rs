@@ -0,0 +1,422 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+};
+
+use hashbrown::HashMap;
+
+use firecore_world_builder::world::{
+    pokedex::{item::ItemId, pokemon::PokemonId},
+    positions::Location,
+    script::{ScriptId, WorldInstruction, WorldScriptData},
+};
+
+use super::{Comparison, Instruction, ScriptData};
+
+const VAR_RESULT: &str = "VAR_RESULT";
+
+/// Instructions a script may run before it is taken to be stuck in a loop
+const STEP_LIMIT: usize = 10_000;
+
+/// The flags, vars, bag and party scripts read and change
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScriptState {
+    pub flags: BTreeSet<String>,
+    pub vars: BTreeMap<String, u16>,
+    pub bag: HashMap<ItemId, u16>,
+    pub party: Vec<PokemonId>,
+    /// If the player is locked in place
+    pub locked: bool,
+}
+
+/// Something a script did, in the order it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptEvent {
+    /// A message was shown, by its label
+    Message(String),
+    /// A yes/no box or menu was answered
+    Choice(u16),
+    SetFlag(String),
+    ClearFlag(String),
+    SetVar(String, u16),
+    AddItem(ItemId, u16),
+    Special(String),
+    Battle,
+    Warp(Location),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpreterError {
+    MissingScript(ScriptId),
+    /// A choice was asked in the script after every answer was used
+    NoAnswer(ScriptId),
+    /// A command that could not be converted, with the script it is in
+    Unsupported(ScriptId, String),
+    StepLimit(ScriptId),
+    /// A script ran past its last instruction, where the decomp falls through into the next label
+    FellOff(ScriptId),
+}
+
+/// Runs converted scripts against a [`ScriptState`] without the game, answering yes/no boxes
+/// and menus with the given answers in order and recording every [`ScriptEvent`].
+///
+/// Movement, sounds and objects are not simulated, and specials only return the value given to
+/// [`Interpreter::special`].
+pub struct Interpreter<'a> {
+    scripts: Cow<'a, HashMap<ScriptId, Vec<Instruction>>>,
+    pub state: ScriptState,
+    pub events: Vec<ScriptEvent>,
+    answers: VecDeque<u16>,
+    specials: HashMap<String, u16>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(scripts: &'a ScriptData) -> Self {
+        Self::with_scripts(Cow::Borrowed(&scripts.scripts))
+    }
+
+    /// Runs scripts lowered for the world, see [`ScriptData::lower`]
+    pub fn world(scripts: &WorldScriptData) -> Interpreter<'static> {
+        Interpreter::with_scripts(Cow::Owned(
+            scripts
+                .scripts
+                .iter()
+                .map(|(id, instructions)| {
+                    (
+                        id.clone(),
+                        instructions
+                            .iter()
+                            .cloned()
+                            .map(Instruction::World)
+                            .collect(),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    fn with_scripts(scripts: Cow<'a, HashMap<ScriptId, Vec<Instruction>>>) -> Self {
+        Self {
+            scripts,
+            state: Default::default(),
+            events: Default::default(),
+            answers: Default::default(),
+            specials: Default::default(),
+        }
+    }
+
+    /// Adds answers for the next yes/no boxes (`1` is yes) and menus (the index of the option)
+    pub fn answer(&mut self, answers: impl IntoIterator<Item = u16>) -> &mut Self {
+        self.answers.extend(answers);
+        self
+    }
+
+    /// Sets the value a special function returns to `specialvar`
+    pub fn special(&mut self, special: impl Into<String>, result: u16) -> &mut Self {
+        self.specials.insert(special.into(), result);
+        self
+    }
+
+    pub fn flag(&self, flag: &str) -> bool {
+        self.state.flags.contains(flag)
+    }
+
+    pub fn var(&self, var: &str) -> u16 {
+        self.state.vars.get(var).copied().unwrap_or_default()
+    }
+
+    /// The labels of the messages shown so far
+    pub fn messages(&self) -> impl Iterator<Item = &str> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            ScriptEvent::Message(message) => Some(message.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Runs a script until it ends, warps or returns from its last call
+    pub fn run(&mut self, script: &str) -> Result<(), InterpreterError> {
+        let mut id = script.to_owned();
+        let mut position = 0;
+        let mut calls = Vec::new();
+        // the result of the last comparison
+        let mut condition = Ordering::Equal;
+
+        for _ in 0..STEP_LIMIT {
+            let instructions = self
+                .scripts
+                .get(&id)
+                .ok_or_else(|| InterpreterError::MissingScript(id.clone()))?;
+
+            let instruction = match instructions.get(position) {
+                Some(instruction) => instruction.clone(),
+                // the decomp would fall through into the next label, which is not known here
+                None => return Err(InterpreterError::FellOff(id)),
+            };
+            position += 1;
+
+            let mut jump = None;
+            let mut call = None;
+
+            match instruction {
+                Instruction::World(instruction) => match instruction {
+                    WorldInstruction::End => return Ok(()),
+                    WorldInstruction::Return => match calls.pop() {
+                        Some((caller, at)) => {
+                            id = caller;
+                            position = at;
+                        }
+                        None => return Ok(()),
+                    },
+                    WorldInstruction::SetVar(var, value) => self.set_var(var, value),
+                    WorldInstruction::SetFlag(flag) => {
+                        self.state.flags.insert(flag.clone());
+                        self.events.push(ScriptEvent::SetFlag(flag));
+                    }
+                    WorldInstruction::SpecialVar(var, special) => {
+                        let result = self.specials.get(&special).copied().unwrap_or_default();
+                        self.events.push(ScriptEvent::Special(special));
+                        self.set_var(var, result);
+                    }
+                    WorldInstruction::Compare(var, value) => condition = self.var(&var).cmp(&value),
+                    WorldInstruction::Call(target) => call = Some(target),
+                    WorldInstruction::GotoIfEq(target) => {
+                        if condition == Ordering::Equal {
+                            jump = Some(target);
+                        }
+                    }
+                    WorldInstruction::GotoIfSet(flag, target) => {
+                        if self.flag(&flag) {
+                            jump = Some(target);
+                        }
+                    }
+                    WorldInstruction::Lock => self.state.locked = true,
+                    WorldInstruction::Release => self.state.locked = false,
+                    WorldInstruction::Msgbox(message, kind) => {
+                        self.events.push(ScriptEvent::Message(message));
+                        if kind.as_deref() == Some("MSGBOX_YESNO") {
+                            self.choose(&id)?;
+                        }
+                    }
+                    WorldInstruction::Message(message) => {
+                        self.events.push(ScriptEvent::Message(message))
+                    }
+                    WorldInstruction::AddItem(item) => self.add_item(item, 1),
+                    WorldInstruction::CheckItemSpace(..) => self.set_var(VAR_RESULT.to_owned(), 1),
+                    WorldInstruction::TrainerBattleSingle => self.events.push(ScriptEvent::Battle),
+                    _ => (),
+                },
+                Instruction::Goto(target) => jump = Some(target),
+                Instruction::GotoIf(comparison, target) => {
+                    if holds(comparison, condition) {
+                        jump = Some(target);
+                    }
+                }
+                Instruction::GotoIfUnset(flag, target) => {
+                    if !self.flag(&flag) {
+                        jump = Some(target);
+                    }
+                }
+                Instruction::CallIf(comparison, target) => {
+                    if holds(comparison, condition) {
+                        call = Some(target);
+                    }
+                }
+                Instruction::CallIfSet(flag, target) => {
+                    if self.flag(&flag) {
+                        call = Some(target);
+                    }
+                }
+                Instruction::CallIfUnset(flag, target) => {
+                    if !self.flag(&flag) {
+                        call = Some(target);
+                    }
+                }
+                Instruction::CheckFlag(flag) => condition = u16::from(self.flag(&flag)).cmp(&1),
+                Instruction::ClearFlag(flag) => {
+                    self.state.flags.remove(&flag);
+                    self.events.push(ScriptEvent::ClearFlag(flag));
+                }
+                Instruction::AddVar(var, value) => {
+                    let value = self.var(&var).wrapping_add(value);
+                    self.set_var(var, value);
+                }
+                Instruction::CopyVar(destination, source) => {
+                    let value = self.var(&source);
+                    self.set_var(destination, value);
+                }
+                Instruction::YesNoBox | Instruction::Multichoice(..) => self.choose(&id)?,
+                Instruction::TrainerBattleSingle(..) => self.events.push(ScriptEvent::Battle),
+                Instruction::GiveItem(item, count)
+                | Instruction::FindItem(item, count)
+                | Instruction::AddItem(item, count) => {
+                    self.add_item(item, count);
+                    self.set_var(VAR_RESULT.to_owned(), 1);
+                }
+                Instruction::Warp(_, location, _) => {
+                    self.events.push(ScriptEvent::Warp(location));
+                    return Ok(());
+                }
+                Instruction::Special(special) => self.events.push(ScriptEvent::Special(special)),
+                Instruction::Unsupported(command) => {
+                    return Err(InterpreterError::Unsupported(id, command))
+                }
+                _ => (),
+            }
+
+            if let Some(target) = call {
+                calls.push((std::mem::replace(&mut id, target), position));
+                position = 0;
+            } else if let Some(target) = jump {
+                id = target;
+                position = 0;
+            }
+        }
+
+        Err(InterpreterError::StepLimit(id))
+    }
+
+    fn choose(&mut self, script: &ScriptId) -> Result<(), InterpreterError> {
+        let answer = self
+            .answers
+            .pop_front()
+            .ok_or_else(|| InterpreterError::NoAnswer(script.clone()))?;
+        self.events.push(ScriptEvent::Choice(answer));
+        self.set_var(VAR_RESULT.to_owned(), answer);
+        Ok(())
+    }
+
+    fn set_var(&mut self, var: String, value: u16) {
+        self.state.vars.insert(var.clone(), value);
+        self.events.push(ScriptEvent::SetVar(var, value));
+    }
+
+    fn add_item(&mut self, item: ItemId, count: u16) {
+        *self.state.bag.entry(item).or_default() += count;
+        self.events.push(ScriptEvent::AddItem(item, count));
+    }
+}
+
+pub(super) fn holds(comparison: Comparison, condition: Ordering) -> bool {
+    match comparison {
+        Comparison::Lt => condition == Ordering::Less,
+        Comparison::Eq => condition == Ordering::Equal,
+        Comparison::Gt => condition == Ordering::Greater,
+        Comparison::Le => condition != Ordering::Greater,
+        Comparison::Ge => condition != Ordering::Less,
+        Comparison::Ne => condition != Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firecore_world_builder::world::script::WorldInstruction;
+
+    use super::{Interpreter, InterpreterError, ScriptEvent};
+    use crate::{Instruction, ScriptData};
+
+    fn world(instruction: WorldInstruction) -> Instruction {
+        Instruction::World(instruction)
+    }
+
+    fn oak() -> ScriptData {
+        let mut data = ScriptData::default();
+        data.scripts.insert(
+            "Oak_EventScript".to_owned(),
+            vec![
+                world(WorldInstruction::Lock),
+                world(WorldInstruction::GotoIfSet(
+                    "FLAG_SYS_POKEMON_GET".to_owned(),
+                    "Oak_EventScript_After".to_owned(),
+                )),
+                world(WorldInstruction::Msgbox(
+                    "Oak_Text_TakeThisOne".to_owned(),
+                    None,
+                )),
+                Instruction::YesNoBox,
+                world(WorldInstruction::Compare("VAR_RESULT".to_owned(), 1)),
+                world(WorldInstruction::GotoIfEq("Oak_EventScript_Yes".to_owned())),
+                world(WorldInstruction::Release),
+                world(WorldInstruction::End),
+            ],
+        );
+        data.scripts.insert(
+            "Oak_EventScript_Yes".to_owned(),
+            vec![
+                world(WorldInstruction::SetFlag("FLAG_SYS_POKEMON_GET".to_owned())),
+                world(WorldInstruction::Release),
+                world(WorldInstruction::End),
+            ],
+        );
+        data.scripts.insert(
+            "Oak_EventScript_After".to_owned(),
+            vec![
+                world(WorldInstruction::Msgbox(
+                    "Oak_Text_RaiseYourMon".to_owned(),
+                    None,
+                )),
+                world(WorldInstruction::Release),
+                world(WorldInstruction::End),
+            ],
+        );
+        data
+    }
+
+    #[test]
+    fn talking_to_oak_before_the_starter() {
+        let data = oak();
+        let mut interpreter = Interpreter::new(&data);
+        interpreter.answer([1]);
+        interpreter.run("Oak_EventScript").unwrap();
+
+        assert!(interpreter.flag("FLAG_SYS_POKEMON_GET"));
+        assert!(!interpreter.state.locked);
+        assert_eq!(
+            interpreter.messages().collect::<Vec<_>>(),
+            ["Oak_Text_TakeThisOne"]
+        );
+        assert!(interpreter.events.contains(&ScriptEvent::Choice(1)));
+
+        // talking again takes the other branch
+        interpreter.run("Oak_EventScript").unwrap();
+        assert_eq!(interpreter.messages().last(), Some("Oak_Text_RaiseYourMon"));
+    }
+
+    #[test]
+    fn declining() {
+        let data = oak();
+        let mut interpreter = Interpreter::new(&data);
+        interpreter.answer([0]);
+        interpreter.run("Oak_EventScript").unwrap();
+
+        assert!(!interpreter.flag("FLAG_SYS_POKEMON_GET"));
+        assert!(interpreter.run("Oak_EventScript").is_err());
+    }
+
+    #[test]
+    fn falling_off() {
+        let mut data = ScriptData::default();
+        data.scripts.insert(
+            "Sign".to_owned(),
+            vec![
+                world(WorldInstruction::Call("Sign_Text".to_owned())),
+                world(WorldInstruction::End),
+            ],
+        );
+        // without a return the call runs past its end
+        data.scripts.insert(
+            "Sign_Text".to_owned(),
+            vec![world(WorldInstruction::Msgbox(
+                "Text_Sign".to_owned(),
+                None,
+            ))],
+        );
+
+        let mut interpreter = Interpreter::new(&data);
+        assert_eq!(
+            interpreter.run("Sign"),
+            Err(InterpreterError::FellOff("Sign_Text".to_owned()))
+        );
+        assert_eq!(interpreter.messages().collect::<Vec<_>>(), ["Text_Sign"]);
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::BTreeSet;
+
+use firecore_world_builder::world::script::{ScriptId, WorldInstruction, WorldScriptData};
+use hashbrown::HashMap;
+
+use crate::{report::LoweringReport, variables::ScriptVariables};
+
+use super::{interpreter::holds, Comparison, Instruction, ScriptData};
+
+type Block = (ScriptId, Vec<WorldInstruction>);
+
+/// Vars from this id are special vars the engine sets, such as `VAR_RESULT`
+const SPECIAL_VARS_START: u16 = 0x8000;
+
+impl ScriptData {
+    /// Rewrites the scripts with [`WorldInstruction`]s only, for engines that do not know the other instructions,
+    /// and renders the messages as text.
+    ///
+    /// A `goto` becomes a `call` followed by a `return`, and conditional jumps the engine cannot test
+    /// split their script into labels of their own (`{script}_Lowered{n}`) to jump between.
+    /// A `goto_if_lt` and the other inequalities test the var against each value the scripts set it to,
+    /// or are left out of their script if those are not known, which the returned report lists
+    /// along with the instructions without an equivalent.
+    pub fn lower(&self, variables: &ScriptVariables) -> (WorldScriptData, LoweringReport) {
+        let mut report = LoweringReport::default();
+
+        let values = var_values(self, variables);
+
+        let scripts = self
+            .scripts
+            .iter()
+            .flat_map(|(id, instructions)| lower(id, instructions, &values, &mut report))
+            .collect();
+
+        let data = WorldScriptData {
+            scripts,
+            messages: self
+                .messages
+                .iter()
+                .map(|(id, message)| (id.clone(), crate::text::render(message)))
+                .collect(),
+        };
+
+        (data, report)
+    }
+}
+
+/// The values each var of the scripts takes: the 0 it starts at and every value `setvar` sets it to.
+///
+/// Special vars and vars that `addvar`, `copyvar` or `specialvar` also change have no known values.
+fn var_values<'a>(
+    scripts: &'a ScriptData,
+    variables: &ScriptVariables,
+) -> HashMap<&'a str, Option<BTreeSet<u16>>> {
+    let mut values = HashMap::<_, Option<BTreeSet<u16>>>::new();
+
+    for instruction in scripts.scripts.values().flatten() {
+        match instruction {
+            Instruction::World(WorldInstruction::SetVar(var, value)) => {
+                if let Some(values) = values
+                    .entry(var.as_str())
+                    .or_insert_with(|| Some(BTreeSet::from([0])))
+                {
+                    values.insert(*value);
+                }
+            }
+            Instruction::World(WorldInstruction::Compare(var, ..)) => {
+                values
+                    .entry(var.as_str())
+                    .or_insert_with(|| Some(BTreeSet::from([0])));
+            }
+            Instruction::World(WorldInstruction::SpecialVar(var, ..))
+            | Instruction::AddVar(var, ..)
+            | Instruction::CopyVar(var, ..) => {
+                values.insert(var.as_str(), None);
+            }
+            _ => (),
+        }
+    }
+
+    for (var, values) in values.iter_mut() {
+        if !matches!(variables.id(var), Some(id) if id < SPECIAL_VARS_START) {
+            *values = None;
+        }
+    }
+
+    values
+}
+
+/// What an inequality comes down to, out of the values its var takes
+enum Equality {
+    Always,
+    Never,
+    /// Holds for the compared value only (`Eq`), or for every value but it (`Ne`)
+    Is(Comparison),
+    /// Holds for these values
+    AnyOf(Vec<u16>),
+}
+
+fn equality(comparison: Comparison, values: &BTreeSet<u16>, compared: u16) -> Equality {
+    let holding = values
+        .iter()
+        .copied()
+        .filter(|value| holds(comparison, value.cmp(&compared)))
+        .collect::<Vec<_>>();
+    if holding.is_empty() {
+        Equality::Never
+    } else if holding.len() == values.len() {
+        Equality::Always
+    } else if holding == [compared] {
+        Equality::Is(Comparison::Eq)
+    } else if holding.len() + 1 == values.len()
+        && values.contains(&compared)
+        && !holding.contains(&compared)
+    {
+        Equality::Is(Comparison::Ne)
+    } else {
+        Equality::AnyOf(holding)
+    }
+}
+
+fn lower(
+    id: &ScriptId,
+    instructions: &[Instruction],
+    values: &HashMap<&str, Option<BTreeSet<u16>>>,
+    report: &mut LoweringReport,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Block = (id.clone(), Vec::new());
+    let mut labels = 0;
+    let mut label = || {
+        labels += 1;
+        format!("{}_Lowered{}", id, labels)
+    };
+    // a `checkflag` makes the next conditions test its flag instead of the last comparison
+    let mut checked: Option<String> = None;
+    let mut compared: Option<(String, u16)> = None;
+    let branch = |checked: Option<&String>, target: ScriptId| match checked {
+        Some(flag) => WorldInstruction::GotoIfSet(flag.clone(), target),
+        None => WorldInstruction::GotoIfEq(target),
+    };
+    // a flag is compared as 0 or 1 against 1
+    let flag_values = BTreeSet::from([0, 1]);
+
+    for instruction in instructions {
+        let equal;
+        let instruction = match instruction {
+            Instruction::GotoIf(comparison, target) | Instruction::CallIf(comparison, target)
+                if !matches!(comparison, Comparison::Eq | Comparison::Ne) =>
+            {
+                let goto = matches!(instruction, Instruction::GotoIf(..));
+                let known = match (&checked, &compared) {
+                    (Some(..), ..) => Some((&flag_values, 1)),
+                    (None, Some((var, value))) => values
+                        .get(var.as_str())
+                        .and_then(Option::as_ref)
+                        .map(|values| (values, *value)),
+                    (None, None) => None,
+                };
+                let (taken, value) = match known {
+                    Some(known) => known,
+                    None => {
+                        report
+                            .branches
+                            .entry(id.clone())
+                            .or_default()
+                            .insert(format!("{:?}", instruction));
+                        continue;
+                    }
+                };
+                equal = match (equality(*comparison, taken, value), goto) {
+                    (Equality::Never, ..) => continue,
+                    (Equality::Always, true) => Instruction::Goto(target.clone()),
+                    (Equality::Always, false) => {
+                        Instruction::World(WorldInstruction::Call(target.clone()))
+                    }
+                    (Equality::Is(comparison), true) => {
+                        Instruction::GotoIf(comparison, target.clone())
+                    }
+                    (Equality::Is(comparison), false) => {
+                        Instruction::CallIf(comparison, target.clone())
+                    }
+                    // only a var takes more than two values
+                    (Equality::AnyOf(holding), goto) => {
+                        let var = compared
+                            .as_ref()
+                            .map(|(var, ..)| var.clone())
+                            .unwrap_or_default();
+                        let to = match goto {
+                            true => target.clone(),
+                            false => label(),
+                        };
+                        for holding in holding {
+                            current.1.extend([
+                                WorldInstruction::Compare(var.clone(), holding),
+                                WorldInstruction::GotoIfEq(to.clone()),
+                            ]);
+                        }
+                        // the comparison is tested again by the next conditions
+                        current.1.push(WorldInstruction::Compare(var, value));
+                        if !goto {
+                            let next = label();
+                            current.1.extend([
+                                WorldInstruction::Call(next.clone()),
+                                WorldInstruction::Return,
+                            ]);
+                            blocks.push((
+                                to,
+                                vec![
+                                    WorldInstruction::Call(target.clone()),
+                                    WorldInstruction::Call(next.clone()),
+                                    WorldInstruction::Return,
+                                ],
+                            ));
+                            blocks.push(std::mem::replace(&mut current, (next, Vec::new())));
+                        }
+                        continue;
+                    }
+                };
+                &equal
+            }
+            instruction => instruction,
+        };
+
+        let code = &mut current.1;
+        match instruction {
+            Instruction::World(WorldInstruction::GotoIfEq(target)) => {
+                code.push(branch(checked.as_ref(), target.clone()))
+            }
+            Instruction::World(instruction) => {
+                if let WorldInstruction::Compare(var, value) = instruction {
+                    checked = None;
+                    compared = Some((var.clone(), *value));
+                }
+                code.push(instruction.clone());
+            }
+            Instruction::Goto(target) => {
+                code.extend([
+                    WorldInstruction::Call(target.clone()),
+                    WorldInstruction::Return,
+                ]);
+            }
+            Instruction::GotoIf(Comparison::Eq, target) => {
+                code.push(branch(checked.as_ref(), target.clone()))
+            }
+            Instruction::GotoIf(Comparison::Ne, target) => {
+                let next = label();
+                code.extend([
+                    branch(checked.as_ref(), next.clone()),
+                    WorldInstruction::Call(target.clone()),
+                    WorldInstruction::Return,
+                ]);
+                blocks.push(std::mem::replace(&mut current, (next, Vec::new())));
+            }
+            Instruction::GotoIfUnset(flag, target) => {
+                let next = label();
+                code.extend([
+                    WorldInstruction::GotoIfSet(flag.clone(), next.clone()),
+                    WorldInstruction::Call(target.clone()),
+                    WorldInstruction::Return,
+                ]);
+                blocks.push(std::mem::replace(&mut current, (next, Vec::new())));
+            }
+            Instruction::CallIf(Comparison::Eq, target) | Instruction::CallIfSet(.., target) => {
+                let (call, next) = (label(), label());
+                let condition = match instruction {
+                    Instruction::CallIfSet(flag, ..) => {
+                        WorldInstruction::GotoIfSet(flag.clone(), call.clone())
+                    }
+                    _ => branch(checked.as_ref(), call.clone()),
+                };
+                code.extend([
+                    condition,
+                    WorldInstruction::Call(next.clone()),
+                    WorldInstruction::Return,
+                ]);
+                blocks.push((
+                    call,
+                    vec![
+                        WorldInstruction::Call(target.clone()),
+                        WorldInstruction::Call(next.clone()),
+                        WorldInstruction::Return,
+                    ],
+                ));
+                blocks.push(std::mem::replace(&mut current, (next, Vec::new())));
+            }
+            Instruction::CallIf(Comparison::Ne, target) | Instruction::CallIfUnset(.., target) => {
+                let next = label();
+                let condition = match instruction {
+                    Instruction::CallIfUnset(flag, ..) => {
+                        WorldInstruction::GotoIfSet(flag.clone(), next.clone())
+                    }
+                    _ => branch(checked.as_ref(), next.clone()),
+                };
+                code.extend([
+                    condition,
+                    WorldInstruction::Call(target.clone()),
+                    WorldInstruction::Call(next.clone()),
+                    WorldInstruction::Return,
+                ]);
+                blocks.push(std::mem::replace(&mut current, (next, Vec::new())));
+            }
+            Instruction::CheckFlag(flag) => checked = Some(flag.clone()),
+            Instruction::TrainerBattleSingle(.., defeated) => {
+                code.push(WorldInstruction::TrainerBattleSingle);
+                // the world's battles do not go on to a script after a win
+                if defeated.is_some() {
+                    report
+                        .skipped
+                        .entry(kind(instruction))
+                        .or_default()
+                        .insert(id.clone());
+                }
+            }
+            Instruction::GiveItem(item, count)
+            | Instruction::FindItem(item, count)
+            | Instruction::AddItem(item, count) => {
+                code.extend((0..*count).map(|_| WorldInstruction::AddItem(*item)))
+            }
+            instruction => {
+                report
+                    .skipped
+                    .entry(kind(instruction))
+                    .or_default()
+                    .insert(id.clone());
+            }
+        }
+    }
+
+    blocks.push(current);
+
+    blocks
+}
+
+/// The name of an instruction's variant
+fn kind(instruction: &Instruction) -> String {
+    let name = format!("{:?}", instruction);
+    match name.split_once('(') {
+        Some((kind, ..)) => kind.to_owned(),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firecore_world_builder::world::script::WorldInstruction;
+
+    use crate::{Comparison, Instruction, Interpreter, ScriptData, ScriptVariables};
+
+    fn world(instruction: WorldInstruction) -> Instruction {
+        Instruction::World(instruction)
+    }
+
+    fn variables() -> ScriptVariables {
+        ScriptVariables {
+            vars: [
+                ("VAR_COUNT".to_owned(), 0x4001),
+                ("VAR_RESULT".to_owned(), 0x800D),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lowered_scripts_run_the_same() {
+        let mut data = ScriptData::default();
+        data.scripts.insert(
+            "Sign".to_owned(),
+            vec![
+                Instruction::GotoIfUnset("FLAG_VISITED".to_owned(), "Sign_First".to_owned()),
+                world(WorldInstruction::Compare("VAR_COUNT".to_owned(), 1)),
+                Instruction::CallIf(Comparison::Ne, "Sign_Count".to_owned()),
+                Instruction::Goto("Sign_After".to_owned()),
+            ],
+        );
+        data.scripts.insert(
+            "Sign_First".to_owned(),
+            vec![
+                world(WorldInstruction::SetFlag("FLAG_VISITED".to_owned())),
+                world(WorldInstruction::Msgbox("Text_First".to_owned(), None)),
+                world(WorldInstruction::End),
+            ],
+        );
+        data.scripts.insert(
+            "Sign_Count".to_owned(),
+            vec![
+                world(WorldInstruction::SetVar("VAR_COUNT".to_owned(), 1)),
+                world(WorldInstruction::Msgbox("Text_Count".to_owned(), None)),
+                world(WorldInstruction::Return),
+            ],
+        );
+        data.scripts.insert(
+            "Sign_After".to_owned(),
+            vec![
+                Instruction::Delay(30),
+                world(WorldInstruction::Msgbox("Text_After".to_owned(), None)),
+                world(WorldInstruction::End),
+            ],
+        );
+
+        let (lowered, report) = data.lower(&variables());
+        assert!(report.branches.is_empty());
+        assert!(report.skipped["Delay"].contains("Sign_After"));
+
+        let mut converted = Interpreter::new(&data);
+        let mut world = Interpreter::world(&lowered);
+        for _ in 0..3 {
+            converted.run("Sign").unwrap();
+            world.run("Sign").unwrap();
+        }
+        assert_eq!(
+            converted.messages().collect::<Vec<_>>(),
+            ["Text_First", "Text_Count", "Text_After", "Text_After"]
+        );
+        assert_eq!(
+            world.messages().collect::<Vec<_>>(),
+            converted.messages().collect::<Vec<_>>()
+        );
+        assert_eq!(world.state, converted.state);
+    }
+
+    /// Runs a script on the converted and the lowered scripts with `VAR_COUNT` counting up to 3
+    fn counting(data: &ScriptData) -> Vec<String> {
+        let (lowered, report) = data.lower(&variables());
+        assert!(report.branches.is_empty());
+
+        let mut converted = Interpreter::new(data);
+        let mut world = Interpreter::world(&lowered);
+        for _ in 0..4 {
+            converted.run("Script").unwrap();
+            world.run("Script").unwrap();
+        }
+        assert_eq!(world.state, converted.state);
+        assert_eq!(
+            world.messages().collect::<Vec<_>>(),
+            converted.messages().collect::<Vec<_>>()
+        );
+        converted.messages().map(str::to_owned).collect()
+    }
+
+    fn count(comparison: Comparison, call: bool) -> ScriptData {
+        let mut data = ScriptData::default();
+        let branch = match call {
+            true => Instruction::CallIf(comparison, "Holds".to_owned()),
+            false => Instruction::GotoIf(comparison, "Holds".to_owned()),
+        };
+        data.scripts.insert(
+            "Script".to_owned(),
+            vec![
+                world(WorldInstruction::Compare("VAR_COUNT".to_owned(), 2)),
+                branch,
+                world(WorldInstruction::Msgbox("Text_Next".to_owned(), None)),
+                world(WorldInstruction::Compare("VAR_COUNT".to_owned(), 0)),
+                Instruction::GotoIf(Comparison::Eq, "Zero".to_owned()),
+                world(WorldInstruction::Compare("VAR_COUNT".to_owned(), 1)),
+                Instruction::GotoIf(Comparison::Eq, "One".to_owned()),
+                world(WorldInstruction::Compare("VAR_COUNT".to_owned(), 2)),
+                Instruction::GotoIf(Comparison::Eq, "Two".to_owned()),
+                world(WorldInstruction::End),
+            ],
+        );
+        data.scripts.insert(
+            "Holds".to_owned(),
+            vec![
+                world(WorldInstruction::Msgbox("Text_Holds".to_owned(), None)),
+                world(match call {
+                    true => WorldInstruction::Return,
+                    false => WorldInstruction::End,
+                }),
+            ],
+        );
+        for (script, value) in [("Zero", 1), ("One", 2), ("Two", 3)] {
+            data.scripts.insert(
+                script.to_owned(),
+                vec![
+                    world(WorldInstruction::SetVar("VAR_COUNT".to_owned(), value)),
+                    world(WorldInstruction::End),
+                ],
+            );
+        }
+        data
+    }
+
+    #[test]
+    fn unequal_comparisons() {
+        // VAR_COUNT is 0, 1, 2 and 3 in turn, compared with 2
+        for (comparison, holds) in [
+            (Comparison::Lt, [true, true, false, false]),
+            (Comparison::Le, [true, true, true, false]),
+            (Comparison::Gt, [false, false, false, true]),
+            (Comparison::Ge, [false, false, true, true]),
+        ] {
+            for call in [false, true] {
+                let messages = counting(&count(comparison, call));
+                let expected = holds
+                    .iter()
+                    .flat_map(|holds| match (holds, call) {
+                        (true, true) => vec!["Text_Holds", "Text_Next"],
+                        (true, false) => vec!["Text_Holds"],
+                        (false, _) => vec!["Text_Next"],
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(messages, expected, "{:?}, call: {}", comparison, call);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_values_leave_out_the_branch() {
+        let mut data = ScriptData::default();
+        data.scripts.insert(
+            "Script".to_owned(),
+            vec![
+                // a special var, set by the engine
+                world(WorldInstruction::Compare("VAR_RESULT".to_owned(), 2)),
+                Instruction::GotoIf(Comparison::Lt, "Other".to_owned()),
+                world(WorldInstruction::Msgbox("Text_Kept".to_owned(), None)),
+                world(WorldInstruction::End),
+            ],
+        );
+
+        let (lowered, report) = data.lower(&variables());
+        // the script is kept without the branch
+        let mut world = Interpreter::world(&lowered);
+        world.run("Script").unwrap();
+        assert_eq!(world.messages().collect::<Vec<_>>(), ["Text_Kept"]);
+        assert!(report.branches["Script"].contains("GotoIf(Lt, \"Other\")"));
+    }
+}
@@ -0,0 +1,127 @@
+use hashbrown::HashMap;
+
+use firecore_world_builder::world::{
+    audio::{SoundId, SoundVariant},
+    pokedex::item::ItemId,
+    positions::{Coordinate, Location},
+    script::{ScriptId, WorldInstruction},
+};
+use script_parser::text::{Line, TextLine};
+use serde::{Deserialize, Serialize};
+
+mod args;
+mod convert;
+mod decompile;
+mod flow;
+mod interpreter;
+mod lower;
+mod movement;
+mod namespace;
+
+pub(crate) use convert::create_script_data;
+pub(crate) use flow::analyze;
+pub(crate) use namespace::Namespaces;
+pub use decompile::{decompile, decompile_world};
+pub use interpreter::{Interpreter, InterpreterError, ScriptEvent, ScriptState};
+pub use movement::MovementStep;
+
+/// Instructions of converted scripts.
+///
+/// Commands the world's script engine already knows are kept as [`WorldInstruction`]s,
+/// every other supported command of the decomp has a variant of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Instruction {
+    World(WorldInstruction),
+    Goto(ScriptId),
+    /// Jumps if the last comparison matches
+    GotoIf(Comparison, ScriptId),
+    GotoIfUnset(String, ScriptId),
+    /// Calls if the last comparison matches
+    CallIf(Comparison, ScriptId),
+    CallIfSet(String, ScriptId),
+    CallIfUnset(String, ScriptId),
+    /// Compares a flag to being set
+    CheckFlag(String),
+    ClearFlag(String),
+    AddVar(String, u16),
+    /// Copies the second var into the first
+    CopyVar(String, String),
+    /// Waits for a number of frames
+    Delay(u16),
+    PlaySound(SoundId, SoundVariant),
+    WaitSound,
+    CloseMessage,
+    /// Asks a yes/no question, leaving the answer in `VAR_RESULT`
+    YesNoBox,
+    /// A battle against a trainer, with its intro and defeat texts and the script run after the player wins
+    TrainerBattleSingle(String, String, String, Option<ScriptId>),
+    /// Shows a menu, leaving the index of the chosen option (or `MULTI_B_PRESSED`) in `VAR_RESULT`
+    Multichoice(Menu),
+    GiveItem(ItemId, u16),
+    /// Adds an item to the bag without a message
+    AddItem(ItemId, u16),
+    /// Gives an item with the message for finding it
+    FindItem(ItemId, u16),
+    /// Moves an object of the map, given by its local id
+    SetObjectPosition(String, Coordinate),
+    RemoveObject(String),
+    AddObject(String),
+    /// Moves an object, given by its local id, through the steps of a movement label
+    ApplyMovement(String, String, Vec<MovementStep>),
+    /// Waits for the movement of an object to finish, where `0` waits for every object
+    WaitMovement(String),
+    /// Warps the player to a map, at a position or where the player is standing
+    Warp(WarpKind, Location, Option<Coordinate>),
+    /// Sets the destination of the next door or warp without warping
+    SetWarp(Location, Option<Coordinate>),
+    /// Sets where escaping (Dig, Escape Rope) from the current map leads
+    SetEscapeWarp(Location, Option<Coordinate>),
+    Special(String),
+    /// A command that could not be converted, as written in the script
+    Unsupported(String),
+}
+
+/// A multichoice menu of the decomp
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Menu {
+    /// The `MULTICHOICE_*` id
+    pub id: String,
+    /// Where the menu is drawn, in tiles
+    pub position: Coordinate,
+    pub options: Vec<String>,
+    /// The option selected first
+    pub default: u8,
+    /// If pressing B closes the menu
+    pub cancel: bool,
+}
+
+/// The transition of a scripted warp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WarpKind {
+    Normal,
+    /// Without a sound
+    Silent,
+    /// Through a door, with the door animation
+    Door,
+    /// Falling through a hole
+    Hole,
+    Teleport,
+}
+
+/// Conditions of the `goto_if_*` and `call_if_*` commands, in the order the games number them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Comparison {
+    Lt,
+    Eq,
+    Gt,
+    Le,
+    Ge,
+    Ne,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScriptData {
+    pub scripts: HashMap<ScriptId, Vec<Instruction>>,
+    /// Messages as decoded game text
+    pub messages: HashMap<String, Vec<TextLine<Line>>>,
+}
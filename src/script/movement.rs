@@ -0,0 +1,49 @@
+use firecore_world_builder::world::positions::Direction;
+use script_parser::movement::{self, Speed, Step};
+use serde::{Deserialize, Serialize};
+
+/// A step of a movement sequence applied to an object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementStep {
+    Walk(Direction, Speed),
+    WalkInPlace(Direction, Speed),
+    Face(Direction),
+    FacePlayer,
+    FaceAwayPlayer,
+    /// Jumps a number of tiles in a direction, where 0 is jumping in place
+    Jump(Direction, u8),
+    /// Waits for a number of frames
+    Delay(u16),
+    SetVisible(bool),
+    Emote(String),
+    /// A movement macro the world has no step for
+    Other(String),
+}
+
+impl From<&Step> for MovementStep {
+    fn from(step: &Step) -> Self {
+        match step {
+            Step::Walk(direction, speed) => Self::Walk(direction_of(*direction), *speed),
+            Step::WalkInPlace(direction, speed) => {
+                Self::WalkInPlace(direction_of(*direction), *speed)
+            }
+            Step::Face(direction) => Self::Face(direction_of(*direction)),
+            Step::FacePlayer => Self::FacePlayer,
+            Step::FaceAwayPlayer => Self::FaceAwayPlayer,
+            Step::Jump(direction, tiles) => Self::Jump(direction_of(*direction), *tiles),
+            Step::Delay(frames) => Self::Delay(*frames),
+            Step::SetVisible(visible) => Self::SetVisible(*visible),
+            Step::Emote(emote) => Self::Emote(emote.clone()),
+            Step::Other(step) => Self::Other(step.clone()),
+        }
+    }
+}
+
+fn direction_of(direction: movement::Direction) -> Direction {
+    match direction {
+        movement::Direction::Down => Direction::Down,
+        movement::Direction::Up => Direction::Up,
+        movement::Direction::Left => Direction::Left,
+        movement::Direction::Right => Direction::Right,
+    }
+}
@@ -0,0 +1,271 @@
+use hashbrown::{HashMap, HashSet};
+use script_parser::inc::{Data, Script};
+
+use crate::map::JsonMapData;
+
+/// Joins a map and a label, which keeps the id a label the decomp's assembler accepts
+const SEPARATOR: &str = "__";
+
+/// The labels of every map's files, to prefix them with their map, `PalletTown__PalletTown_EventScript_Sign`.
+///
+/// Built from all maps before any file is rewritten, so a reference to a label of another map
+/// is pointed at that map's definition too.
+#[derive(Debug, Default)]
+pub(crate) struct Namespaces {
+    /// The maps defining each label
+    maps: HashMap<String, Vec<String>>,
+    /// Labels of the files outside the maps, which keep their name
+    shared: HashSet<String>,
+}
+
+impl Namespaces {
+    pub(crate) fn new<'a>(
+        maps: impl IntoIterator<Item = (&'a str, &'a Script)>,
+        shared: impl IntoIterator<Item = &'a Script>,
+    ) -> Self {
+        let mut namespaces = Self::default();
+        for (map, script) in maps {
+            let defined = namespaces.maps.entry(script.name.clone()).or_default();
+            if !defined.iter().any(|defined| defined == map) {
+                defined.push(map.to_owned());
+            }
+        }
+        namespaces
+            .shared
+            .extend(shared.into_iter().map(|script| script.name.clone()));
+        namespaces
+    }
+
+    /// Prefixes the labels `map` defines in `scripts` and points the arguments of every script at the new labels.
+    ///
+    /// A label is looked up in `map` first, then in the files outside the maps, then in the one map defining it.
+    /// A label several other maps define keeps its name and is reported.
+    /// Files outside the maps are rewritten without a `map`.
+    pub(crate) fn rewrite(&self, map: Option<&str>, source: &str, scripts: &mut [Script]) {
+        for script in scripts.iter_mut() {
+            if let Some(map) = map {
+                script.name = namespaced(map, &script.name);
+            }
+            let mut rename = |label: &mut String| self.rename(map, source, label);
+            for command in script.commands.iter_mut() {
+                command.arguments.iter_mut().for_each(&mut rename);
+            }
+            for data in script.data.iter_mut() {
+                if let Data::Byte(values) | Data::Half(values) | Data::Word(values) = data {
+                    values.iter_mut().for_each(&mut rename);
+                }
+            }
+        }
+    }
+
+    /// Points the scripts of a map's object, bg and coord events at the new labels, see [`Namespaces::rewrite`]
+    pub(crate) fn rewrite_events(&self, map: &str, data: &mut JsonMapData) {
+        let source = format!("data/maps/{}/map.json", map);
+        let rename = |label: &mut String| self.rename(Some(map), &source, label);
+        for event in data.object_events.iter_mut() {
+            rename(&mut event.script);
+        }
+        for script in data
+            .bg_events
+            .iter_mut()
+            .flat_map(|event| event.script.as_mut())
+        {
+            rename(script);
+        }
+        for script in data
+            .coord_events
+            .iter_mut()
+            .flat_map(|event| event.script.as_mut())
+        {
+            rename(script);
+        }
+    }
+
+    fn rename(&self, map: Option<&str>, source: &str, label: &mut String) {
+        let defined = match self.maps.get(label.as_str()) {
+            Some(defined) => defined,
+            None => return,
+        };
+        let map = match map.filter(|map| defined.iter().any(|defined| defined == map)) {
+            Some(map) => map,
+            None if self.shared.contains(label.as_str()) => return,
+            None => match defined.as_slice() {
+                [map] => map.as_str(),
+                _ => {
+                    eprintln!(
+                        "{}: {} is defined by the maps {} and keeps its name",
+                        source,
+                        label,
+                        defined.join(", ")
+                    );
+                    return;
+                }
+            },
+        };
+        *label = namespaced(map, label);
+    }
+}
+
+fn namespaced(map: &str, label: &str) -> String {
+    format!("{}{}{}", map, SEPARATOR, label)
+}
+
+#[cfg(test)]
+mod tests {
+    use script_parser::inc::{self, Script};
+
+    use super::Namespaces;
+    use crate::map::JsonMapData;
+
+    fn map(script: &str) -> JsonMapData {
+        serde_json::from_value(serde_json::json!({
+            "id": "MAP_TEST",
+            "name": "Test",
+            "layout": "LAYOUT_TEST",
+            "music": "MUS_TEST",
+            "region_map_section": "MAPSEC_TEST",
+            "requires_flash": false,
+            "weather": "WEATHER_NONE",
+            "map_type": "MAP_TYPE_TOWN",
+            "allow_cycling": true,
+            "allow_escaping": false,
+            "allow_running": true,
+            "show_map_name": true,
+            "floor_number": 0,
+            "battle_scene": "MAP_BATTLE_SCENE_NORMAL",
+            "connections": null,
+            "object_events": [],
+            "warp_events": [],
+            "bg_events": [{
+                "type": "sign",
+                "x": 1,
+                "y": 2,
+                "elevation": 0,
+                "player_facing_dir": "BG_EVENT_PLAYER_FACING_NORTH",
+                "script": script,
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn scripts(source: &str) -> Vec<Script> {
+        let (scripts, diagnostics) = inc::parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        scripts
+    }
+
+    fn commands(script: &Script) -> Vec<String> {
+        script.commands.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn same_label_in_two_maps() {
+        let source = "EventScript_Sign::
+    msgbox Text_Sign, MSGBOX_SIGN
+    end
+Text_Sign::
+    .string \"A sign.$\"
+";
+
+        let mut pallet = map("EventScript_Sign");
+        let mut pallet_scripts = scripts(source);
+        let mut viridian = map("EventScript_Sign");
+        let mut viridian_scripts = scripts(source);
+
+        let namespaces = Namespaces::new(
+            pallet_scripts
+                .iter()
+                .map(|script| ("PalletTown", script))
+                .chain(
+                    viridian_scripts
+                        .iter()
+                        .map(|script| ("ViridianCity", script)),
+                ),
+            std::iter::empty(),
+        );
+        namespaces.rewrite(Some("PalletTown"), "pallet", &mut pallet_scripts);
+        namespaces.rewrite_events("PalletTown", &mut pallet);
+        namespaces.rewrite(Some("ViridianCity"), "viridian", &mut viridian_scripts);
+        namespaces.rewrite_events("ViridianCity", &mut viridian);
+
+        assert_eq!(pallet_scripts[0].name, "PalletTown__EventScript_Sign");
+        assert_eq!(
+            pallet_scripts[0].commands[0].arguments,
+            ["PalletTown__Text_Sign", "MSGBOX_SIGN"]
+        );
+        assert_eq!(viridian_scripts[0].name, "ViridianCity__EventScript_Sign");
+        assert_eq!(
+            pallet.bg_events[0].script.as_deref(),
+            Some("PalletTown__EventScript_Sign")
+        );
+        assert_eq!(
+            viridian.bg_events[0].script.as_deref(),
+            Some("ViridianCity__EventScript_Sign")
+        );
+
+        // the new labels are read back as labels
+        let renamed = scripts(&format!("{}::\n    end\n", pallet_scripts[0].name));
+        assert_eq!(renamed[0].name, "PalletTown__EventScript_Sign");
+    }
+
+    #[test]
+    fn references_to_other_maps() {
+        let mut pallet_scripts = scripts(
+            "PalletTown_EventScript_Oak::
+    goto Route1_EventScript_Oak
+Text_Sign::
+    .string \"Pallet Town$\"
+",
+        );
+        let route1_scripts = scripts(
+            "Route1_EventScript_Oak::
+    end
+Text_Sign::
+    .string \"Route 1$\"
+",
+        );
+        let viridian_scripts = scripts(
+            "Text_Sign::
+    .string \"Viridian City$\"
+",
+        );
+        let mut shared_scripts = scripts(
+            "EventScript_Heal::
+    call Route1_EventScript_Oak
+    msgbox Text_Sign, MSGBOX_SIGN
+    goto EventScript_Heal
+",
+        );
+
+        let namespaces = Namespaces::new(
+            pallet_scripts
+                .iter()
+                .map(|script| ("PalletTown", script))
+                .chain(route1_scripts.iter().map(|script| ("Route1", script)))
+                .chain(
+                    viridian_scripts
+                        .iter()
+                        .map(|script| ("ViridianCity", script)),
+                ),
+            shared_scripts.iter(),
+        );
+        namespaces.rewrite(Some("PalletTown"), "pallet", &mut pallet_scripts);
+        namespaces.rewrite(None, "shared", &mut shared_scripts);
+
+        // a label only another map defines is that map's
+        assert_eq!(
+            commands(&pallet_scripts[0]),
+            ["goto Route1__Route1_EventScript_Oak"]
+        );
+        assert_eq!(shared_scripts[0].name, "EventScript_Heal");
+        assert_eq!(
+            commands(&shared_scripts[0]),
+            [
+                "call Route1__Route1_EventScript_Oak",
+                // defined by three maps, so it cannot be told which
+                "msgbox Text_Sign, MSGBOX_SIGN",
+                "goto EventScript_Heal",
+            ]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use script_parser::text::{self, Line, LineBreak, Segment, TextLine};
+
+/// Writes decoded game text as the pages of a world message, where the player's name is `%p`.
+///
+/// Scrolled lines stay on their page, other variables and glyphs keep their name in braces
+/// and control codes are left out.
+pub(crate) fn render(message: &[TextLine<Line>]) -> Vec<Vec<String>> {
+    text::pages(message)
+        .into_iter()
+        .map(|page| page.into_iter().map(|line| render_line(line)).collect())
+        .collect()
+}
+
+fn render_line(line: &[Segment]) -> String {
+    let mut text = String::new();
+    for segment in line {
+        match segment {
+            Segment::Text(run) => text.push_str(run),
+            Segment::Variable(name) if name == "PLAYER" => text.push_str("%p"),
+            Segment::Variable(name) | Segment::Glyph(name) => {
+                text.push('{');
+                text.push_str(name);
+                text.push('}');
+            }
+            _ => (),
+        }
+    }
+    text
+}
+
+/// Writes decoded game text back as the decomp writes it, a line per `.string` with its break,
+/// the way [`script_parser::text::Charmap::decode`] reads it.
+pub(crate) fn source(message: &[TextLine<Line>]) -> Vec<String> {
+    message
+        .iter()
+        .map(|line| {
+            let mut text = String::new();
+            for segment in line.text.iter() {
+                source_segment(&mut text, segment);
+            }
+            text.push_str(match line.end {
+                LineBreak::NewLine => "\\n",
+                LineBreak::Scroll => "\\l",
+                LineBreak::Page => "\\p",
+                LineBreak::End => "$",
+            });
+            text
+        })
+        .collect()
+}
+
+fn source_segment(text: &mut String, segment: &Segment) {
+    let code = |text: &mut String, name: &str, arguments: &[String]| {
+        text.push('{');
+        text.push_str(name);
+        for argument in arguments {
+            text.push(' ');
+            text.push_str(argument);
+        }
+        text.push('}');
+    };
+    match segment {
+        Segment::Text(run) => {
+            for c in run.chars() {
+                if matches!(c, '"' | '\\' | '$' | '{') {
+                    text.push('\\');
+                }
+                text.push(c);
+            }
+        }
+        Segment::Variable(name) | Segment::Glyph(name) => code(text, name, &[]),
+        Segment::Color(name, arguments) | Segment::Control(name, arguments) => {
+            code(text, name, arguments)
+        }
+        Segment::Pause(frames) => code(text, "PAUSE", &[frames.to_string()]),
+        Segment::PauseUntilPress => code(text, "PAUSE_UNTIL_PRESS", &[]),
+        // songs and sound effects are told apart by their names
+        Segment::Sound(sound) => match sound.starts_with("MUS_") {
+            true => code(text, "PLAY_BGM", std::slice::from_ref(sound)),
+            false => code(text, "PLAY_SE", std::slice::from_ref(sound)),
+        },
+    }
+}
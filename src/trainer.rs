@@ -0,0 +1,589 @@
+use std::{collections::BTreeSet, str::FromStr};
+
+use hashbrown::HashSet;
+
+use firecore_world_builder::world::pokedex::{
+    moves::owned::SavedMove,
+    pokemon::{owned::SavedPokemon, stat::StatSet, Gender, Nature, Pokemon},
+    Dex,
+};
+use script_parser::{
+    inc::Command,
+    text::Charmap,
+    trainer::{
+        party::{Ivs, Stats, TrainerPokemon},
+        Trainer,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tinystr::TinyStr16;
+
+use crate::{
+    constants::Constants, NameMappings, Names, ParsedData, Problems, RematchTexts, Scripts,
+};
+
+/// Highest value an IV can have
+const MAX_PER_STAT_IVS: u16 = 31;
+
+/// Natures in the order the games index them (`personality % 25`)
+const NATURES: [Nature; 25] = [
+    Nature::Hardy,
+    Nature::Lonely,
+    Nature::Brave,
+    Nature::Adamant,
+    Nature::Naughty,
+    Nature::Bold,
+    Nature::Docile,
+    Nature::Relaxed,
+    Nature::Impish,
+    Nature::Lax,
+    Nature::Timid,
+    Nature::Hasty,
+    Nature::Serious,
+    Nature::Jolly,
+    Nature::Naive,
+    Nature::Modest,
+    Nature::Mild,
+    Nature::Quiet,
+    Nature::Bashful,
+    Nature::Rash,
+    Nature::Calm,
+    Nature::Gentle,
+    Nature::Sassy,
+    Nature::Careful,
+    Nature::Quirky,
+];
+
+/// Trainer data the world's `NpcTrainer` has no place for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrainerInfo {
+    /// Display name of the trainer class, such as "Youngster"
+    pub class: Option<String>,
+    /// Music played when the trainer spots the player
+    pub music: Option<TinyStr16>,
+    pub ai: BTreeSet<AiFlag>,
+    /// VS Seeker rematches, if the trainer has any
+    pub rematch: Option<Rematch>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rematch {
+    pub encounter: Vec<Vec<String>>,
+    pub defeat: Vec<Vec<String>>,
+    /// Party for each rematch, in the order the VS Seeker goes through them.
+    /// Stages the VS Seeker skips, or whose trainer cannot be found, are `None`
+    pub parties: Vec<Option<Vec<SavedPokemon>>>,
+}
+
+/// Battle AI scripts a trainer uses (`AI_SCRIPT_*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AiFlag {
+    CheckBadMove,
+    TryToFaint,
+    CheckViability,
+    SetupFirstTurn,
+    Risky,
+    PreferStrongestMove,
+    PreferBatonPass,
+    DoubleBattle,
+    HpAware,
+    Roaming,
+    Safari,
+    FirstBattle,
+}
+
+impl FromStr for AiFlag {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s
+            .strip_prefix("AI_SCRIPT_")
+            .or_else(|| s.strip_prefix("AI_FLAG_"))
+            .ok_or(())?;
+        Ok(match s {
+            "CHECK_BAD_MOVE" => Self::CheckBadMove,
+            "TRY_TO_FAINT" => Self::TryToFaint,
+            "CHECK_VIABILITY" => Self::CheckViability,
+            "SETUP_FIRST_TURN" => Self::SetupFirstTurn,
+            "RISKY" => Self::Risky,
+            "PREFER_STRONGEST_MOVE" => Self::PreferStrongestMove,
+            "PREFER_BATON_PASS" => Self::PreferBatonPass,
+            "DOUBLE_BATTLE" => Self::DoubleBattle,
+            "HP_AWARE" => Self::HpAware,
+            "ROAMING" => Self::Roaming,
+            "SAFARI" => Self::Safari,
+            "FIRST_BATTLE" => Self::FirstBattle,
+            _ => return Err(()),
+        })
+    }
+}
+
+pub(crate) fn info(
+    mappings: &NameMappings,
+    data: &ParsedData,
+    constants: &Constants,
+    id: &str,
+    trainer: &Trainer,
+    problems: &Problems,
+    label: &str,
+) -> TrainerInfo {
+    let music = trainer
+        .music
+        .split('|')
+        .map(str::trim)
+        .find(|music| music.starts_with("TRAINER_ENCOUNTER_MUSIC_"))
+        .and_then(|music| {
+            mappings.trainers.music.get(music).copied().or_else(|| {
+                eprintln!("Cannot find encounter music {}", music);
+                None
+            })
+        });
+
+    let class = mappings
+        .trainers
+        .classes
+        .get(&trainer.class)
+        .or_else(|| data.class_names.get(&trainer.class))
+        .cloned()
+        .or_else(|| {
+            eprintln!("Cannot get trainer class name for {}", trainer.class);
+            None
+        });
+
+    let ai = trainer
+        .ai_flags
+        .iter()
+        .filter(|flag| flag.as_str() != "0")
+        .flat_map(|flag| {
+            flag.parse().ok().or_else(|| {
+                eprintln!("Unknown AI flag {}", flag);
+                None
+            })
+        })
+        .collect();
+
+    TrainerInfo {
+        class,
+        music,
+        ai,
+        rematch: rematch(data, constants, id, problems, label),
+    }
+}
+
+/// The VS Seeker rematches of a trainer, recording the texts it has none for as problems of `label`
+fn rematch(
+    data: &ParsedData,
+    constants: &Constants,
+    id: &str,
+    problems: &Problems,
+    label: &str,
+) -> Option<Rematch> {
+    let rematches = data.rematches.get(id)?;
+
+    let parties = rematches
+        .iter()
+        .map(|id| {
+            let id = id.as_ref()?;
+            let trainer = data.trainers.get(id).or_else(|| {
+                eprintln!("Cannot get rematch trainer {}", id);
+                None
+            })?;
+            let party = data.parties.get(&trainer.party).or_else(|| {
+                eprintln!("Cannot get party for rematch trainer {}", id);
+                None
+            })?;
+            Some(party_of(data, constants, trainer, party))
+        })
+        .collect();
+
+    let texts = data.rematch_texts.get(id);
+    if texts.is_none() {
+        problems
+            .entry(label.to_owned())
+            .or_default()
+            .insert(format!("no trainerbattle_rematch for trainer {}", id));
+    }
+    let message = |text: Option<&String>| {
+        let text = text?;
+        match data.messages.get(text) {
+            Some(message) => Some(crate::text::render(message.value())),
+            None => {
+                problems
+                    .entry(label.to_owned())
+                    .or_default()
+                    .insert(format!(
+                        "rematch text {} of trainer {} is not defined",
+                        text, id
+                    ));
+                None
+            }
+        }
+    };
+    let encounter = message(texts.map(|(encounter, _)| encounter)).unwrap_or_default();
+    let defeat = message(texts.map(|(_, defeat)| defeat)).unwrap_or_default();
+
+    Some(Rematch {
+        encounter,
+        defeat,
+        parties,
+    })
+}
+
+pub(crate) fn party_of(
+    data: &ParsedData,
+    constants: &Constants,
+    trainer: &Trainer,
+    party: &[TrainerPokemon],
+) -> Vec<SavedPokemon> {
+    party
+        .iter()
+        .zip(personalities(
+            &data.charmap,
+            trainer,
+            &data.species_names,
+            party,
+        ))
+        .flat_map(|(p, personality)| {
+            let pokemon = data.pokedex.try_get(&constants.species(&p.species)?)?;
+            let mut saved = SavedPokemon {
+                pokemon: pokemon.id,
+                level: p.level,
+                gender: match p.gender.as_deref() {
+                    Some("TRAINER_MON_MALE") => Some(Gender::Male),
+                    Some("TRAINER_MON_FEMALE") => Some(Gender::Female),
+                    _ => gender(pokemon, personality),
+                },
+                nature: p
+                    .nature
+                    .as_deref()
+                    .and_then(nature_named)
+                    .or_else(|| Some(nature(personality))),
+                ivs: match p.ivs {
+                    Ivs::Scaled(iv) => StatSet::uniform(fixed_iv(iv)),
+                    Ivs::Stats(ivs) => stat_set(ivs),
+                },
+                evs: p.evs.map(stat_set).unwrap_or_default(),
+                nickname: p.nickname.clone(),
+                ..Default::default()
+            };
+            saved.item = p.item.as_deref().and_then(|item| constants.item(item));
+            if let Some(moves) = p.moves.as_ref() {
+                saved.moves.extend(
+                    moves
+                        .iter()
+                        .flat_map(|m| constants.move_id(m))
+                        .map(SavedMove::from),
+                );
+            }
+            Some(saved)
+        })
+        .collect()
+}
+
+/// Badge given by a `setflag FLAG_BADGE0x_GET` in a trainer's scripts
+pub(crate) fn badge<'a>(
+    mappings: &NameMappings,
+    mut commands: impl Iterator<Item = &'a Command>,
+) -> Option<TinyStr16> {
+    commands.find_map(|command| match command.command.as_str() {
+        "setflag" => mappings
+            .trainers
+            .badges
+            .get(command.arguments.get(0)?)
+            .copied(),
+        _ => None,
+    })
+}
+
+/// The text a defeated trainer says when talked to again, the first `msgbox` after the battle.
+///
+/// `goto`s are followed, while branches (such as the one to a rematch) are not.
+pub(crate) fn post_battle_message(data: &ParsedData, commands: &[Command]) -> Option<String> {
+    let mut commands = commands.to_vec();
+    let mut visited = HashSet::new();
+    loop {
+        let mut next = None;
+        for command in commands.iter() {
+            match command.command.as_str() {
+                "msgbox" => return command.arguments.get(0).cloned(),
+                "goto" => {
+                    next = command.arguments.get(0);
+                    break;
+                }
+                "end" | "return" => return None,
+                _ => (),
+            }
+        }
+        let next = next?.clone();
+        if !visited.insert(next.clone()) {
+            return None;
+        }
+        commands = data.scripts.get(&next)?.commands.clone();
+    }
+}
+
+/// The intro and defeat texts of each trainer's `trainerbattle_rematch TRAINER, Text_Intro, Text_Defeat`,
+/// keeping one of them for a trainer battled by several
+pub(crate) fn rematch_texts(scripts: &Scripts) -> RematchTexts {
+    let mut texts = RematchTexts::new();
+    for script in scripts.iter() {
+        for command in script
+            .commands
+            .iter()
+            .filter(|command| command.command == "trainerbattle_rematch")
+        {
+            if let [id, encounter, defeat, ..] = command.arguments.as_slice() {
+                texts
+                    .entry(id.clone())
+                    .or_insert_with(|| (encounter.clone(), defeat.clone()));
+            }
+        }
+    }
+    texts
+}
+
+/// Gym leaders mark every trainer in their gym as defeated (`set_gym_trainers`)
+pub(crate) fn disables_gym<'a>(mut commands: impl Iterator<Item = &'a Command>) -> bool {
+    commands.any(|command| command.command == "set_gym_trainers")
+}
+
+/// Converts the 0-255 `.iv` of a trainer party into the IV every stat gets in game
+pub(crate) fn fixed_iv(iv: u8) -> u8 {
+    (iv as u16 * MAX_PER_STAT_IVS / 255) as u8
+}
+
+/// Personality values of a trainer's party, matching `CreateNPCTrainerParty`.
+///
+/// The low byte only depends on the trainer (double battle, or class gender),
+/// the rest is a name hash that keeps accumulating over the whole party.
+pub(crate) fn personalities(
+    charmap: &Charmap,
+    trainer: &Trainer,
+    species_names: &Names,
+    party: &[TrainerPokemon],
+) -> Vec<u32> {
+    let base: u32 = if trainer.double_battle {
+        0x80
+    } else if trainer.music.contains("F_TRAINER_FEMALE") {
+        0x78
+    } else {
+        0x88
+    };
+
+    let trainer_hash = name_hash(charmap, trainer.name.as_deref().unwrap_or_default());
+
+    let mut hash = 0u32;
+
+    party
+        .iter()
+        .map(|pokemon| {
+            let species_hash = species_names
+                .get(&pokemon.species)
+                .map(|name| name_hash(charmap, name))
+                .unwrap_or_else(|| {
+                    eprintln!("Cannot get species name for {}", pokemon.species);
+                    0
+                });
+            hash = hash.wrapping_add(trainer_hash).wrapping_add(species_hash);
+            base.wrapping_add(hash << 8)
+        })
+        .collect()
+}
+
+/// Gender from the personality value, the same way `GetGenderFromSpeciesAndPersonality` does
+pub(crate) fn gender(pokemon: &Pokemon, personality: u32) -> Option<Gender> {
+    pokemon
+        .breeding
+        .gender
+        .map(|ratio| gender_of(ratio, personality))
+}
+
+/// Gender from the female ratio in eighths, compared with the low byte of the personality
+fn gender_of(ratio: u8, personality: u32) -> Gender {
+    match ratio {
+        0 => Gender::Male,
+        8.. => Gender::Female,
+        eighths => match (eighths as u32 * 255 / 8) > (personality & 0xFF) {
+            true => Gender::Female,
+            false => Gender::Male,
+        },
+    }
+}
+
+pub(crate) fn nature(personality: u32) -> Nature {
+    NATURES[(personality % 25) as usize]
+}
+
+/// Nature from its constant, such as `NATURE_ADAMANT`
+fn nature_named(name: &str) -> Option<Nature> {
+    let name = name.strip_prefix("NATURE_")?;
+    NATURES
+        .iter()
+        .find(|nature| format!("{:?}", nature).eq_ignore_ascii_case(name))
+        .copied()
+}
+
+fn stat_set(stats: Stats) -> StatSet<u8> {
+    StatSet {
+        hp: stats.hp,
+        atk: stats.attack,
+        def: stats.defense,
+        sp_atk: stats.sp_attack,
+        sp_def: stats.sp_defense,
+        speed: stats.speed,
+    }
+}
+
+/// Sum of the bytes of a name, which the charmap encodes
+fn name_hash(charmap: &Charmap, name: &str) -> u32 {
+    let (bytes, unknown) = charmap.encode(name);
+    if !unknown.is_empty() {
+        eprintln!("Cannot encode {} of name {}", unknown.join(", "), name);
+    }
+    bytes.into_iter().map(u32::from).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use firecore_world_builder::world::pokedex::pokemon::{Gender, Nature};
+    use hashbrown::HashMap;
+    use script_parser::{
+        text::Charmap,
+        trainer::{party::TrainerPokemon, Trainer},
+    };
+
+    fn charmap() -> Charmap {
+        let mut charmap = "' ' = 00\n'&' = 2D\n'(' = 5C\n')' = 5D\n".to_owned();
+        for (letter, byte) in ('A'..='Z').zip(0xBB..) {
+            charmap.push_str(&format!("'{}' = {:02X}\n", letter, byte));
+        }
+        Charmap::parse(&charmap)
+    }
+
+    fn party(species: &[&str]) -> Vec<TrainerPokemon> {
+        species
+            .iter()
+            .map(|species| TrainerPokemon {
+                level: 11,
+                species: species.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn species_names() -> HashMap<String, String> {
+        ["RATTATA", "EKANS", "PIDGEY"]
+            .into_iter()
+            .map(|name| (format!("SPECIES_{}", name), name.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn youngster_ben() {
+        let trainer = Trainer {
+            music: "TRAINER_ENCOUNTER_MUSIC_MALE".to_owned(),
+            name: Some("BEN".to_owned()),
+            ..Default::default()
+        };
+        let personalities = super::personalities(
+            &charmap(),
+            &trainer,
+            &species_names(),
+            &party(&["SPECIES_RATTATA", "SPECIES_EKANS"]),
+        );
+
+        // BEN hashes to 579, RATTATA to 1383 and EKANS to 980, and the hash keeps adding up
+        assert_eq!(personalities, [0x88 + (1962 << 8), 0x88 + (3521 << 8)]);
+        assert_eq!(super::nature(personalities[0]), Nature::Impish);
+        assert_eq!(super::nature(personalities[1]), Nature::Serious);
+        // 0x88 is above the 50% ratio of 127
+        assert_eq!(super::gender_of(4, personalities[0]), Gender::Male);
+    }
+
+    #[test]
+    fn lass_janice() {
+        let trainer = Trainer {
+            music: "F_TRAINER_FEMALE | TRAINER_ENCOUNTER_MUSIC_FEMALE".to_owned(),
+            name: Some("JANICE".to_owned()),
+            ..Default::default()
+        };
+        let personalities = super::personalities(
+            &charmap(),
+            &trainer,
+            &species_names(),
+            &party(&["SPECIES_PIDGEY", "SPECIES_PIDGEY"]),
+        );
+
+        assert_eq!(personalities, [0x78 + (2340 << 8), 0x78 + (4680 << 8)]);
+        assert_eq!(super::nature(personalities[0]), Nature::Timid);
+        assert_eq!(super::nature(personalities[1]), Nature::Hardy);
+        // 0x78 is below the 50% ratio of 127, but above the 12.5% ratio of 31
+        assert_eq!(super::gender_of(4, personalities[0]), Gender::Female);
+        assert_eq!(super::gender_of(1, personalities[0]), Gender::Male);
+        assert_eq!(super::gender_of(0, personalities[0]), Gender::Male);
+        assert_eq!(super::gender_of(8, personalities[0]), Gender::Female);
+    }
+
+    #[test]
+    fn double_battles() {
+        let trainer = Trainer {
+            music: "F_TRAINER_FEMALE | TRAINER_ENCOUNTER_MUSIC_FEMALE".to_owned(),
+            name: Some("A&(B)".to_owned()),
+            double_battle: true,
+            ..Default::default()
+        };
+        let personalities = super::personalities(
+            &charmap(),
+            &trainer,
+            &species_names(),
+            &party(&["SPECIES_EKANS"]),
+        );
+
+        // the punctuation of the name is hashed too
+        let name = 0xBB + 0x2D + 0x5C + 0xBC + 0x5D;
+        assert_eq!(personalities, [0x80 + ((name + 980) << 8)]);
+    }
+
+    #[test]
+    fn fixed_ivs() {
+        assert_eq!(super::fixed_iv(0), 0);
+        assert_eq!(super::fixed_iv(100), 12);
+        assert_eq!(super::fixed_iv(200), 24);
+        assert_eq!(super::fixed_iv(255), 31);
+    }
+
+    #[test]
+    fn rematch_texts() {
+        let (parsed, diagnostics) = script_parser::inc::parse(
+            "Route3_EventScript_Ben::
+    trainerbattle_single TRAINER_YOUNGSTER_BEN, Route3_Text_BenIntro, Route3_Text_BenDefeat
+    specialvar VAR_RESULT, ShouldTryRematchBattle
+    goto_if_eq VAR_RESULT, TRUE, Route3_EventScript_BenRematch
+    end
+Route3_EventScript_BenRematch::
+    trainerbattle_rematch TRAINER_YOUNGSTER_BEN, Route3_Text_BenRematchIntro, Route3_Text_BenDefeat
+    end
+Route3_EventScript_Janice::
+    trainerbattle_rematch TRAINER_LASS_JANICE
+    end
+",
+        );
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let scripts = parsed
+            .into_iter()
+            .map(|script| (script.name.clone(), script))
+            .collect();
+
+        let texts = super::rematch_texts(&scripts);
+
+        // a command without its texts is left out
+        assert_eq!(texts.len(), 1);
+        assert_eq!(
+            texts["TRAINER_YOUNGSTER_BEN"],
+            (
+                "Route3_Text_BenRematchIntro".to_owned(),
+                "Route3_Text_BenDefeat".to_owned()
+            )
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use firecore_world_builder::world::script::WorldInstruction;
+use serde::{Deserialize, Serialize};
+
+use crate::{Instruction, ParsedData, ScriptData};
+
+/// Every flag (`FLAG_*`) and var (`VAR_*`) of `flags.h` and `vars.h` with its id in the games
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScriptVariables {
+    pub flags: BTreeMap<String, u16>,
+    pub vars: BTreeMap<String, u16>,
+}
+
+/// Flags and vars used by scripts that are not in [`ScriptVariables`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UnknownVariables {
+    pub flags: BTreeSet<String>,
+    pub vars: BTreeSet<String>,
+}
+
+impl ScriptVariables {
+    pub fn new(data: &ParsedData) -> Self {
+        fn table(defines: &crate::Defines, prefix: &str) -> BTreeMap<String, u16> {
+            defines
+                .iter()
+                .filter(|(name, ..)| name.starts_with(prefix))
+                .flat_map(|(name, id)| Some((name.clone(), u16::try_from(*id).ok()?)))
+                .collect()
+        }
+
+        Self {
+            flags: table(&data.flags, "FLAG_"),
+            vars: table(&data.vars, "VAR_"),
+        }
+    }
+
+    /// The id of a var, from its name or written as an id
+    pub fn id(&self, var: &str) -> Option<u16> {
+        self.vars.get(var).copied().or_else(|| parse_id(var))
+    }
+
+    /// Checks every flag and var of the scripts against the tables,
+    /// replacing the known ones with their id if `numeric` is set.
+    pub fn check(&self, scripts: &mut ScriptData, numeric: bool) -> UnknownVariables {
+        let mut unknown = UnknownVariables::default();
+
+        for instruction in scripts.scripts.values_mut().flatten() {
+            let (name, table, missing) = match instruction {
+                Instruction::World(
+                    WorldInstruction::SetFlag(flag) | WorldInstruction::GotoIfSet(flag, ..),
+                )
+                | Instruction::GotoIfUnset(flag, ..)
+                | Instruction::CallIfSet(flag, ..)
+                | Instruction::CallIfUnset(flag, ..)
+                | Instruction::CheckFlag(flag)
+                | Instruction::ClearFlag(flag) => (flag, &self.flags, &mut unknown.flags),
+                Instruction::World(
+                    WorldInstruction::SetVar(var, ..)
+                    | WorldInstruction::Compare(var, ..)
+                    | WorldInstruction::SpecialVar(var, ..),
+                )
+                | Instruction::AddVar(var, ..) => (var, &self.vars, &mut unknown.vars),
+                Instruction::CopyVar(a, b) => {
+                    Self::resolve(&self.vars, a, numeric, &mut unknown.vars);
+                    (b, &self.vars, &mut unknown.vars)
+                }
+                _ => continue,
+            };
+            Self::resolve(table, name, numeric, missing);
+        }
+
+        unknown
+    }
+
+    fn resolve(
+        table: &BTreeMap<String, u16>,
+        name: &mut String,
+        numeric: bool,
+        unknown: &mut BTreeSet<String>,
+    ) {
+        // scripts may also use an id directly
+        match table.get(name.as_str()).copied().or_else(|| parse_id(name)) {
+            Some(id) => {
+                if numeric {
+                    *name = id.to_string();
+                }
+            }
+            None => {
+                unknown.insert(name.clone());
+            }
+        }
+    }
+}
+
+fn parse_id(id: &str) -> Option<u16> {
+    match id.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => id.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firecore_world_builder::world::script::WorldInstruction;
+
+    use super::ScriptVariables;
+    use crate::{Instruction, ScriptData};
+
+    fn variables() -> ScriptVariables {
+        ScriptVariables {
+            flags: [("FLAG_BEAT_BEN".to_owned(), 0x4B1)].into_iter().collect(),
+            vars: [
+                ("VAR_MAP_SCENE_ROUTE3".to_owned(), 0x4050),
+                ("VAR_RESULT".to_owned(), 0x800D),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    fn scripts() -> ScriptData {
+        let mut scripts = ScriptData::default();
+        scripts.scripts.insert(
+            "Route3_EventScript_Ben".to_owned(),
+            vec![
+                Instruction::World(WorldInstruction::SetFlag("FLAG_BEAT_BEN".to_owned())),
+                Instruction::ClearFlag("FLAG_HIDE_BEN".to_owned()),
+                Instruction::CheckFlag("0x4B2".to_owned()),
+                Instruction::World(WorldInstruction::SetVar(
+                    "VAR_MAP_SCENE_ROUTE3".to_owned(),
+                    1,
+                )),
+                Instruction::World(WorldInstruction::Compare("16400".to_owned(), 1)),
+                Instruction::CopyVar("VAR_RESULT".to_owned(), "VAR_BEN_COUNT".to_owned()),
+            ],
+        );
+        scripts
+    }
+
+    fn names(scripts: &ScriptData) -> Vec<String> {
+        scripts.scripts["Route3_EventScript_Ben"]
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::World(
+                    WorldInstruction::SetFlag(name)
+                    | WorldInstruction::SetVar(name, ..)
+                    | WorldInstruction::Compare(name, ..),
+                )
+                | Instruction::ClearFlag(name)
+                | Instruction::CheckFlag(name) => name.clone(),
+                Instruction::CopyVar(a, b) => format!("{} {}", a, b),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn names_and_ids() {
+        let mut scripts = scripts();
+        let unknown = variables().check(&mut scripts, false);
+
+        // literal ids are known, unknown names are recorded
+        assert_eq!(
+            unknown.flags.into_iter().collect::<Vec<_>>(),
+            ["FLAG_HIDE_BEN"]
+        );
+        assert_eq!(
+            unknown.vars.into_iter().collect::<Vec<_>>(),
+            ["VAR_BEN_COUNT"]
+        );
+        assert_eq!(
+            names(&scripts),
+            [
+                "FLAG_BEAT_BEN",
+                "FLAG_HIDE_BEN",
+                "0x4B2",
+                "VAR_MAP_SCENE_ROUTE3",
+                "16400",
+                "VAR_RESULT VAR_BEN_COUNT",
+            ]
+        );
+    }
+
+    #[test]
+    fn numeric_ids() {
+        let mut scripts = scripts();
+        let unknown = variables().check(&mut scripts, true);
+
+        assert_eq!(unknown.flags.len() + unknown.vars.len(), 2);
+        // unknown names have no id to be replaced with
+        assert_eq!(
+            names(&scripts),
+            [
+                "1201",
+                "FLAG_HIDE_BEN",
+                "1202",
+                "16464",
+                "16400",
+                "32781 VAR_BEN_COUNT",
+            ]
+        );
+    }
+}